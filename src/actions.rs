@@ -1,28 +1,34 @@
 use baddies::Baddie;
-use game::PlayerId;
+use pickups::Pickup;
+use simulation::PlayerId;
 use player::PlayerBody;
 use ggez::graphics::Point2;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Action {
     Game(GameAction),
     Player(PlayerAction, PlayerId),
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum PlayerAction {
     Move(Option<MoveDirection>),
-    Jump,
+    // analog movement, from -1.0 (full left) to 1.0 (full right)
+    Steer(f32),
+    // pressed or released, releasing it early makes a shorter jump
+    Jump(bool),
     Dump(bool),
     Shield(bool),
+    // Only generated by the simulation itself, never recorded
     Collides(Entity),
-    Spawn(Point2),
+    Spawn(#[serde(with = "::serialization::point2")] Point2),
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Entity {
     Baddie(Baddie),
     Player(PlayerBody),
+    Pickup(Pickup),
 }
 
 impl From<Baddie> for Entity {
@@ -31,6 +37,12 @@ impl From<Baddie> for Entity {
     }
 }
 
+impl From<Pickup> for Entity {
+    fn from(pickup: Pickup) -> Self {
+        Entity::Pickup(pickup)
+    }
+}
+
 impl From<PlayerBody> for Entity {
     fn from(body: PlayerBody) -> Self {
         Entity::Player(body)
@@ -49,17 +61,28 @@ impl From<MoveDirection> for PlayerAction {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum MoveDirection {
     Left,
     Right,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum GameAction {
+    // Toggles the pause
     Pause,
+    // Pauses the game, even if it already is
+    Suspend,
     Quit,
+    // Starts a new game with the same players
+    Restart,
     Spawn(PlayerId),
+    // Frees the slot of the player
+    Leave(PlayerId),
+    // The controller of this player was unplugged
+    Disconnect(PlayerId),
+    // The player gets another controller
+    Reconnect(PlayerId, PlayerId),
 }
 
 impl Into<Action> for GameAction {
@@ -1,41 +1,193 @@
+use config::Level;
 use constants::*;
 use resources::Resources;
 
 use ggez::{Context, GameResult};
-use graphics::{self, Color, Rect, Vector2};
-use rand::{thread_rng, Rng};
+use ggez::graphics::{self, Color, Point2, Rect, Vector2};
+use rand::Rng;
 use rand::distributions::{Range, Sample};
+use std::cmp::Ordering;
+use std::f32::consts::PI;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Baddie {
+    #[serde(with = "::serialization::rect")]
     pub body: Rect,
     pub color: BaddieColor,
     pub face: BaddieFace,
 
+    #[serde(with = "::serialization::vector2")]
     speed: Vector2,
+    #[serde(with = "::serialization::point2")]
+    last_position: Point2,
+    movement: Movement,
+    // ticks since the spawn
+    age: u32,
+}
+
+// How a baddie moves, on top of falling
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Movement {
+    Straight,
+    // goes left and right, changing side every period ticks
+    Zigzag { speed: f32, period: u32 },
+    // drifts around its spawn column, a full cycle every period ticks
+    Sine { amplitude: f32, period: u32 },
+    // goes sideways, toward the center first, and bounces off the walls
+    Bounce { speed: f32 },
+    // falls faster and faster
+    Accelerate { acceleration: f32, max_speed: f32 },
+    // slowly goes sideways toward the nearest player
+    Homing { speed: f32 },
+    // stops falling for duration ticks, delay ticks after its spawn
+    Hover { delay: u32, duration: u32 },
+}
+
+impl Movement {
+    // Picks one of the movements, straight when there is none
+    pub fn pick<R: Rng>(rng: &mut R, movements: &[Movement]) -> Movement {
+        if movements.is_empty() {
+            Movement::Straight
+        } else {
+            movements[rng.gen_range(0, movements.len())]
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        use self::Movement::*;
+
+        let positive = match *self {
+            Straight | Hover { .. } => 0.0,
+            Zigzag { speed, .. } | Bounce { speed } | Homing { speed } => speed,
+            Sine { amplitude, .. } => amplitude,
+            Accelerate { acceleration, .. } => acceleration,
+        };
+        if positive.is_nan() || positive < 0.0 {
+            return Err(format!("{:?} must not be negative", self));
+        }
+
+        match *self {
+            Zigzag { period: 0, .. } | Sine { period: 0, .. } => {
+                Err(format!("{:?}: period must be positive", self))
+            }
+            Accelerate { max_speed, .. } if max_speed.is_nan() || max_speed <= 0.0 => {
+                Err(format!("{:?}: max_speed must be positive", self))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl Baddie {
-    pub fn new() -> Baddie {
-        let mut rng = thread_rng();
+    pub fn new<R: Rng>(rng: &mut R, level: &Level) -> Baddie {
+        let size = Range::new(level.baddie_min_size, level.baddie_max_size).sample(rng);
+        let x = Range::new(0.0, WIDTH - size).sample(rng);
+        let speed = Range::new(level.baddie_min_speed, level.baddie_max_speed).sample(rng);
+
+        // only the first attributes are used in the easiest levels
+        let color = COLORS[rng.gen_range(0, level.colors)];
+        let face = FACES[rng.gen_range(0, level.faces)];
+        let movement = Movement::pick(rng, &level.movements);
+
+        Baddie::spawn(x, size, speed, color, face, movement)
+    }
 
-        let size = Range::new(20.0, 50.0).sample(&mut rng);
-        let x = Range::new(0.0, WIDTH - size).sample(&mut rng);
+    // A baddie entering the arena from the top, at x
+    pub fn spawn(
+        x: f32,
+        size: f32,
+        speed: f32,
+        color: BaddieColor,
+        face: BaddieFace,
+        movement: Movement,
+    ) -> Baddie {
+        // bouncing baddies go toward the center first
+        let side_speed = match movement {
+            Movement::Bounce { speed } if x + size / 2.0 > WIDTH / 2.0 => -speed,
+            Movement::Bounce { speed } => speed,
+            _ => 0.0,
+        };
 
         Baddie {
             body: Rect::new(x, -size, size, size),
-            speed: Vector2::new(0.0, Range::new(1.5, 3.0).sample(&mut rng)),
-            color: rng.gen(),
-            face: rng.gen(),
+            last_position: Point2::new(x, -size),
+            speed: Vector2::new(side_speed, speed),
+            color,
+            face,
+            movement,
+            age: 0,
         }
     }
 
-    pub fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
-        self.body.translate(self.speed);
+    // targets are the positions of the players,
+    // time_scale slows the baddie down during a slow-motion
+    pub fn update(&mut self, targets: &[Point2], time_scale: f32) -> GameResult<()> {
+        use self::Movement::*;
+
+        self.last_position = self.body.point();
+
+        let mut velocity = self.speed;
+        match self.movement {
+            Straight | Bounce { .. } => (),
+            Zigzag { speed, period } => {
+                velocity.x = if (self.age / period) % 2 == 0 {
+                    speed
+                } else {
+                    -speed
+                };
+            }
+            Sine { amplitude, period } => {
+                let phase = |age: u32| (age % period) as f32 / period as f32 * 2.0 * PI;
+                velocity.x = amplitude * (phase(self.age + 1).sin() - phase(self.age).sin());
+            }
+            Accelerate {
+                acceleration,
+                max_speed,
+            } => {
+                self.speed.y = (self.speed.y + acceleration).min(max_speed.max(self.speed.y));
+                velocity = self.speed;
+            }
+            Homing { speed } => {
+                let center = self.body.point() + Vector2::new(self.body.w, self.body.h) / 2.0;
+                let nearest = targets.iter().min_by(|a, b| {
+                    let (da, db) = (**a - center, **b - center);
+                    (da.x * da.x + da.y * da.y)
+                        .partial_cmp(&(db.x * db.x + db.y * db.y))
+                        .unwrap_or(Ordering::Equal)
+                });
+                if let Some(target) = nearest {
+                    velocity.x = (target.x - center.x).max(-speed).min(speed);
+                }
+            }
+            Hover { delay, duration } => {
+                if self.age >= delay && self.age - delay < duration {
+                    velocity.y = 0.0;
+                }
+            }
+        }
+
+        self.body.translate(velocity * time_scale);
+
+        // sideways movements stay in the arena
+        if self.body.x < 0.0 || self.body.x + self.body.w > WIDTH {
+            self.body.x = self.body.x.max(0.0).min(WIDTH - self.body.w);
+            self.speed.x = -self.speed.x;
+        }
+
+        self.age += 1;
         Ok(())
     }
 
-    pub fn draw(&self, res: &Resources, ctx: &mut Context) -> GameResult<()> {
+    // Moves sideways toward target, by strength at most
+    pub fn pull(&mut self, target: Point2, strength: f32) {
+        let center = self.body.x + self.body.w / 2.0;
+        let shift = (target.x - center).max(-strength).min(strength);
+        self.body.x = (self.body.x + shift).max(0.0).min(WIDTH - self.body.w);
+    }
+
+    // alpha is the progress between the previous update and the current one
+    pub fn draw(&self, res: &Resources, alpha: f32, ctx: &mut Context) -> GameResult<()> {
         use self::graphics::*;
 
         // get bg & infos
@@ -47,8 +199,10 @@ impl Baddie {
 
         let scale = Point2::new(bw / iw, bh / ih);
 
+        let dest = self.last_position + (self.body.point() - self.last_position) * alpha;
+
         let params = DrawParam {
-            dest: self.body.point(),
+            dest,
             scale,
             ..Default::default()
         };
@@ -58,13 +212,24 @@ impl Baddie {
     }
 }
 
-impl Default for Baddie {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+pub const COLORS: [BaddieColor; 4] = [
+    BaddieColor::Brown,
+    BaddieColor::Green,
+    BaddieColor::Blue,
+    BaddieColor::Yellow,
+];
+
+pub const FACES: [BaddieFace; 6] = [
+    BaddieFace::Bad,
+    BaddieFace::Happy,
+    BaddieFace::Horrified,
+    BaddieFace::Sad,
+    BaddieFace::Sick,
+    BaddieFace::Wink,
+];
 
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Rand)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BaddieColor {
     Brown,
     Green,
@@ -83,7 +248,8 @@ impl Into<Color> for BaddieColor {
     }
 }
 
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Rand)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BaddieFace {
     Bad,
     Happy,
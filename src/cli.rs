@@ -0,0 +1,141 @@
+use clap::{App, Arg};
+use ggez_dodger::launch::{self, Mode};
+use ggez_dodger::waves;
+use std::path::PathBuf;
+
+pub struct Options {
+    pub launch: launch::Options,
+    pub fullscreen: bool,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub log_level: Option<String>,
+}
+
+fn is_parsable<T: ::std::str::FromStr>(value: String) -> Result<(), String> {
+    value
+        .parse::<T>()
+        .map(|_| ())
+        .map_err(|_| format!("invalid value '{}'", value))
+}
+
+impl Options {
+    pub fn from_args() -> Options {
+        let matches = App::new("ggez-dodger")
+            .version(crate_version!())
+            .about("A kind of dodger game")
+            .arg(
+                Arg::with_name("mode")
+                    .long("mode")
+                    .value_name("MODE")
+                    .possible_values(&["play", "replay", "headless"])
+                    .requires_ifs(&[("replay", "replay"), ("headless", "replay")])
+                    .help("What to run, defaults to replay when --replay is given, play otherwise"),
+            )
+            .arg(
+                Arg::with_name("seed")
+                    .long("seed")
+                    .value_name("SEED")
+                    .validator(is_parsable::<u64>)
+                    .help("Seed of the run, random by default"),
+            )
+            .arg(
+                Arg::with_name("players")
+                    .long("players")
+                    .value_name("PLAYERS")
+                    .use_delimiter(true)
+                    .help("Players spawned at startup: a keyboard scheme (arrows, zqsd...) or a controller id"),
+            )
+            .arg(
+                Arg::with_name("fullscreen")
+                    .long("fullscreen")
+                    .help("Starts in fullscreen"),
+            )
+            .arg(
+                Arg::with_name("width")
+                    .long("width")
+                    .value_name("WIDTH")
+                    .validator(is_parsable::<u32>)
+                    .help("Width of the window"),
+            )
+            .arg(
+                Arg::with_name("height")
+                    .long("height")
+                    .value_name("HEIGHT")
+                    .validator(is_parsable::<u32>)
+                    .help("Height of the window"),
+            )
+            .arg(
+                Arg::with_name("log-level")
+                    .long("log-level")
+                    .value_name("LEVEL")
+                    .possible_values(&["error", "warn", "info", "debug", "trace"])
+                    .help("Log level, overrides RUST_LOG"),
+            )
+            .arg(
+                Arg::with_name("replay")
+                    .long("replay")
+                    .value_name("FILE")
+                    .help("Replay file to watch"),
+            )
+            .arg(
+                Arg::with_name("headless")
+                    .long("headless")
+                    .requires("replay")
+                    .conflicts_with("mode")
+                    .help("Plays the replay without any window, same as --mode headless"),
+            )
+            .arg(
+                Arg::with_name("record")
+                    .long("record")
+                    .value_name("FILE")
+                    .help("Records the session to a replay file"),
+            )
+            .arg(
+                Arg::with_name("tuning")
+                    .long("tuning")
+                    .value_name("FILE")
+                    .help("Configuration file applied to the running game each time it changes"),
+            )
+            .arg(
+                Arg::with_name("no-focus-pause")
+                    .long("no-focus-pause")
+                    .help("Keeps the game running when the window loses focus"),
+            )
+            .arg(
+                Arg::with_name("waves")
+                    .long("waves")
+                    .value_name("SET")
+                    .default_value(waves::ENDLESS)
+                    .help("Wave set to play, from resources/waves.toml"),
+            )
+            .get_matches();
+
+        let replay = matches.value_of("replay").map(PathBuf::from);
+        let mode = match (matches.value_of("mode"), matches.is_present("headless"), replay) {
+            (Some("headless"), _, Some(replay)) | (_, true, Some(replay)) => {
+                Mode::Headless(replay)
+            }
+            (Some("replay"), _, Some(replay)) | (None, _, Some(replay)) => Mode::Replay(replay),
+            _ => Mode::Play,
+        };
+
+        Options {
+            launch: launch::Options {
+                mode,
+                seed: matches.value_of("seed").map(|seed| seed.parse().unwrap()),
+                players: matches
+                    .values_of("players")
+                    .map(|players| players.map(String::from).collect())
+                    .unwrap_or_default(),
+                record: matches.value_of("record").map(PathBuf::from),
+                tuning: matches.value_of("tuning").map(PathBuf::from),
+                focus_pause: !matches.is_present("no-focus-pause"),
+                waves: matches.value_of("waves").unwrap().to_owned(),
+            },
+            fullscreen: matches.is_present("fullscreen"),
+            width: matches.value_of("width").map(|width| width.parse().unwrap()),
+            height: matches.value_of("height").map(|height| height.parse().unwrap()),
+            log_level: matches.value_of("log-level").map(String::from),
+        }
+    }
+}
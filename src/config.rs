@@ -0,0 +1,603 @@
+use baddies::{Movement, COLORS, FACES};
+use pickups::PickupKind;
+use constants::*;
+use ggez::{Context, GameError, GameResult};
+use std::collections::btree_map::Entry;
+use std::io::Read;
+use toml::{self, Value};
+
+pub const CONFIG_PATH: &str = "/config.toml";
+
+// Gameplay tuning values, loaded from CONFIG_PATH.
+// Missing entries keep their default value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub radius: f32,
+    pub tolerance: f32,
+    pub ground_height: f32,
+
+    pub jump_height: f32,
+    pub jump_attenuation: f32,
+    pub fast_attenuation: f32,
+    pub player_speed: f32,
+    pub player_damping: f32,
+    pub flying_damping: f32,
+    // a jump pressed at most jump_buffer ticks before landing still happens
+    pub jump_buffer: u32,
+    // the player can still jump coyote_time ticks after leaving the ground
+    pub coyote_time: u32,
+    // releasing the jump button early multiplies the upward speed by jump_cut,
+    // otherwise every jump has the same height
+    pub variable_jump: bool,
+    pub jump_cut: f32,
+
+    pub start_player_life: i32,
+    pub game_over: GameOver,
+
+    // the shield energy goes from 0 to 1, the shield is lowered once empty
+    // and can't be raised again for shield_cooldown ticks
+    pub shield_drain: f32,
+    pub shield_recharge: f32,
+    pub shield_cooldown: u32,
+
+    // every combo_step captures in a row, each capture is worth one more point,
+    // up to combo_max_multiplier points
+    pub combo_step: u32,
+    pub combo_max_multiplier: u32,
+    // without any capture for combo_timeout ticks, the combo loses a step
+    pub combo_timeout: u32,
+
+    pub pickup_size: f32,
+    pub pickup_speed: f32,
+    // baddies speed is multiplied by slow_motion_factor during a slow-motion
+    pub slow_motion_factor: f32,
+    // how fast a magnet draws the baddies sideways
+    pub magnet_strength: f32,
+
+    // what makes the game go from a level to the next one
+    pub difficulty: Difficulty,
+    pub levels: Vec<Level>,
+
+    pub pickups: Pickups,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    // seconds played
+    Time,
+    // sum of the players scores
+    Score,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameOver {
+    // once every player is dead, the dead ones wait for the others
+    AllDead,
+    // as soon as a player dies, for versus games
+    FirstDead,
+}
+
+// How baddies spawn, from the start of the level to the start of the next one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    // in seconds or points, depending on the difficulty
+    pub start: u32,
+    pub spawn_frequency: u32,
+    pub baddie_min_size: f32,
+    pub baddie_max_size: f32,
+    pub baddie_min_speed: f32,
+    pub baddie_max_speed: f32,
+    // number of baddie colors and faces in play
+    pub colors: usize,
+    pub faces: usize,
+    // picked at random for each baddie, straight when empty
+    #[serde(default)]
+    pub movements: Vec<Movement>,
+}
+
+// How often each kind of pickup spawns, and how long its effect lasts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pickups {
+    pub extra_life: PickupRule,
+    pub wildcard: PickupRule,
+    pub slow_motion: PickupRule,
+    pub magnet: PickupRule,
+    pub shield: PickupRule,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PickupRule {
+    // on average, ticks between two spawns, 0 for never
+    pub frequency: u32,
+    // in ticks, an extra life doesn't need any
+    #[serde(default)]
+    pub duration: u32,
+}
+
+impl Default for Pickups {
+    fn default() -> Self {
+        Pickups {
+            extra_life: PickupRule::new(2400, 0),
+            wildcard: PickupRule::new(1800, 300),
+            slow_motion: PickupRule::new(2400, 300),
+            magnet: PickupRule::new(1800, 480),
+            shield: PickupRule::new(1800, 300),
+        }
+    }
+}
+
+impl Pickups {
+    pub fn rule(&self, kind: PickupKind) -> &PickupRule {
+        match kind {
+            PickupKind::ExtraLife => &self.extra_life,
+            PickupKind::Wildcard => &self.wildcard,
+            PickupKind::SlowMotion => &self.slow_motion,
+            PickupKind::Magnet => &self.magnet,
+            PickupKind::Shield => &self.shield,
+        }
+    }
+}
+
+impl PickupRule {
+    fn new(frequency: u32, duration: u32) -> PickupRule {
+        PickupRule {
+            frequency,
+            duration,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            radius: 32.0,
+            tolerance: 2.0,
+            ground_height: 100.0,
+
+            jump_height: 25.0,
+            jump_attenuation: 1.5,
+            fast_attenuation: 3.0,
+            player_speed: 10.0,
+            player_damping: 2.0,
+            flying_damping: 1.0,
+            jump_buffer: 5,
+            coyote_time: 5,
+            variable_jump: true,
+            jump_cut: 0.5,
+
+            start_player_life: 3,
+            game_over: GameOver::AllDead,
+
+            shield_drain: 0.01,
+            shield_recharge: 0.004,
+            shield_cooldown: 120,
+
+            combo_step: 3,
+            combo_max_multiplier: 5,
+            combo_timeout: 180,
+
+            pickup_size: 32.0,
+            pickup_speed: 2.0,
+            slow_motion_factor: 0.5,
+            magnet_strength: 1.5,
+
+            difficulty: Difficulty::Time,
+            levels: vec![
+                Level::new(0, 14, (20.0, 50.0), (1.5, 2.5), 2, 3, vec![]),
+                Level::new(30, 12, (20.0, 50.0), (1.5, 3.0), 3, 4, vec![]),
+                Level::new(
+                    60,
+                    10,
+                    (20.0, 50.0),
+                    (1.5, 3.0),
+                    4,
+                    6,
+                    vec![
+                        Movement::Straight,
+                        Movement::Zigzag {
+                            speed: 1.0,
+                            period: 60,
+                        },
+                        Movement::Sine {
+                            amplitude: 40.0,
+                            period: 120,
+                        },
+                    ],
+                ),
+                Level::new(
+                    120,
+                    8,
+                    (20.0, 60.0),
+                    (2.0, 3.5),
+                    4,
+                    6,
+                    vec![
+                        Movement::Straight,
+                        Movement::Bounce { speed: 1.5 },
+                        Movement::Accelerate {
+                            acceleration: 0.02,
+                            max_speed: 6.0,
+                        },
+                        Movement::Hover {
+                            delay: 60,
+                            duration: 60,
+                        },
+                    ],
+                ),
+                Level::new(
+                    180,
+                    6,
+                    (25.0, 70.0),
+                    (2.5, 4.0),
+                    4,
+                    6,
+                    vec![
+                        Movement::Straight,
+                        Movement::Bounce { speed: 2.0 },
+                        Movement::Homing { speed: 0.5 },
+                        Movement::Accelerate {
+                            acceleration: 0.03,
+                            max_speed: 7.0,
+                        },
+                    ],
+                ),
+            ],
+
+            pickups: Pickups::default(),
+        }
+    }
+}
+
+impl Level {
+    fn new(
+        start: u32,
+        spawn_frequency: u32,
+        (baddie_min_size, baddie_max_size): (f32, f32),
+        (baddie_min_speed, baddie_max_speed): (f32, f32),
+        colors: usize,
+        faces: usize,
+        movements: Vec<Movement>,
+    ) -> Level {
+        Level {
+            start,
+            spawn_frequency,
+            baddie_min_size,
+            baddie_max_size,
+            baddie_min_speed,
+            baddie_max_speed,
+            colors,
+            faces,
+            movements,
+        }
+    }
+
+    fn validate(&self, name: &str) -> GameResult<()> {
+        let positive = [
+            ("baddie_min_size", self.baddie_min_size),
+            ("baddie_min_speed", self.baddie_min_speed),
+        ];
+
+        for &(field, value) in &positive {
+            if value.is_nan() || value <= 0.0 {
+                return Err(GameError::ConfigError(format!(
+                    "{}.{} must be positive, got {}",
+                    name, field, value
+                )));
+            }
+        }
+
+        if self.spawn_frequency == 0 {
+            return Err(GameError::ConfigError(format!(
+                "{}.spawn_frequency must be positive, got 0",
+                name
+            )));
+        }
+
+        if self.baddie_min_size >= self.baddie_max_size {
+            return Err(GameError::ConfigError(format!(
+                "{}.baddie_min_size ({}) must be lower than baddie_max_size ({})",
+                name, self.baddie_min_size, self.baddie_max_size
+            )));
+        }
+
+        if self.baddie_max_size >= WIDTH {
+            return Err(GameError::ConfigError(format!(
+                "{}.baddie_max_size must be lower than {}, got {}",
+                name, WIDTH, self.baddie_max_size
+            )));
+        }
+
+        if self.baddie_min_speed >= self.baddie_max_speed {
+            return Err(GameError::ConfigError(format!(
+                "{}.baddie_min_speed ({}) must be lower than baddie_max_speed ({})",
+                name, self.baddie_min_speed, self.baddie_max_speed
+            )));
+        }
+
+        if self.colors == 0 || self.colors > COLORS.len() {
+            return Err(GameError::ConfigError(format!(
+                "{}.colors must be between 1 and {}, got {}",
+                name,
+                COLORS.len(),
+                self.colors
+            )));
+        }
+
+        if self.faces == 0 || self.faces > FACES.len() {
+            return Err(GameError::ConfigError(format!(
+                "{}.faces must be between 1 and {}, got {}",
+                name,
+                FACES.len(),
+                self.faces
+            )));
+        }
+
+        for movement in &self.movements {
+            movement
+                .validate()
+                .map_err(|e| GameError::ConfigError(format!("{}.movements: {}", name, e)))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    // Loads CONFIG_PATH, or the default configuration if there is no such file
+    pub fn load(ctx: &mut Context) -> GameResult<Config> {
+        if !ctx.filesystem.exists(CONFIG_PATH) {
+            info!("No {} found, using the default configuration", CONFIG_PATH);
+            return Ok(Config::default());
+        }
+
+        let mut content = String::new();
+        ctx.filesystem.open(CONFIG_PATH)?.read_to_string(&mut content)?;
+        Config::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> GameResult<Config> {
+        let config: Config = toml::from_str(content)
+            .map_err(|e| GameError::ConfigError(format!("Invalid configuration: {}", e)))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Like parse, but the missing entries keep their value in self
+    pub fn parse_over(&self, content: &str) -> GameResult<Config> {
+        let invalid = |e: String| GameError::ConfigError(format!("Invalid configuration: {}", e));
+
+        let mut value = Value::try_from(self).map_err(|e| invalid(e.to_string()))?;
+        let tuning: Value = toml::from_str(content).map_err(|e| invalid(e.to_string()))?;
+        merge(&mut value, tuning);
+
+        let config: Config = value.try_into().map_err(|e| invalid(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub(crate) fn validate(&self) -> GameResult<()> {
+        let positive = [
+            ("radius", self.radius),
+            ("ground_height", self.ground_height),
+            ("jump_height", self.jump_height),
+            ("jump_attenuation", self.jump_attenuation),
+            ("fast_attenuation", self.fast_attenuation),
+            ("player_speed", self.player_speed),
+            ("player_damping", self.player_damping),
+            ("flying_damping", self.flying_damping),
+            ("pickup_size", self.pickup_size),
+            ("pickup_speed", self.pickup_speed),
+        ];
+
+        for &(name, value) in &positive {
+            if value.is_nan() || value <= 0.0 {
+                return Err(GameError::ConfigError(format!(
+                    "{} must be positive, got {}",
+                    name, value
+                )));
+            }
+        }
+
+        if self.tolerance.is_nan() || self.tolerance < 0.0 || self.tolerance >= self.radius {
+            return Err(GameError::ConfigError(format!(
+                "tolerance must be between 0 and radius, got {}",
+                self.tolerance
+            )));
+        }
+
+        // the players must fit above the ground
+        if self.max_y() <= 0.0 {
+            return Err(GameError::ConfigError(format!(
+                "ground_height + radius must be lower than {}, got {}",
+                HEIGHT,
+                self.ground_height + self.radius
+            )));
+        }
+
+        if self.jump_cut.is_nan() || self.jump_cut < 0.0 || self.jump_cut > 1.0 {
+            return Err(GameError::ConfigError(format!(
+                "jump_cut must be between 0 and 1, got {}",
+                self.jump_cut
+            )));
+        }
+
+        let rates = [
+            ("shield_drain", self.shield_drain),
+            ("shield_recharge", self.shield_recharge),
+        ];
+
+        for &(name, value) in &rates {
+            if value.is_nan() || value <= 0.0 || value > 1.0 {
+                return Err(GameError::ConfigError(format!(
+                    "{} must be between 0 (excluded) and 1, got {}",
+                    name, value
+                )));
+            }
+        }
+
+        let counts = [
+            ("combo_step", self.combo_step),
+            ("combo_max_multiplier", self.combo_max_multiplier),
+            ("combo_timeout", self.combo_timeout),
+        ];
+
+        for &(name, value) in &counts {
+            if value == 0 {
+                return Err(GameError::ConfigError(format!(
+                    "{} must be positive, got 0",
+                    name
+                )));
+            }
+        }
+
+        if self.pickup_size >= WIDTH {
+            return Err(GameError::ConfigError(format!(
+                "pickup_size must be lower than {}, got {}",
+                WIDTH, self.pickup_size
+            )));
+        }
+
+        if self.slow_motion_factor.is_nan() || self.slow_motion_factor <= 0.0
+            || self.slow_motion_factor > 1.0
+        {
+            return Err(GameError::ConfigError(format!(
+                "slow_motion_factor must be between 0 (excluded) and 1, got {}",
+                self.slow_motion_factor
+            )));
+        }
+
+        if self.magnet_strength.is_nan() || self.magnet_strength < 0.0 {
+            return Err(GameError::ConfigError(format!(
+                "magnet_strength must not be negative, got {}",
+                self.magnet_strength
+            )));
+        }
+
+        // an extra life has no duration
+        let timed = [
+            ("wildcard", &self.pickups.wildcard),
+            ("slow_motion", &self.pickups.slow_motion),
+            ("magnet", &self.pickups.magnet),
+            ("shield", &self.pickups.shield),
+        ];
+        for &(name, rule) in &timed {
+            if rule.frequency > 0 && rule.duration == 0 {
+                return Err(GameError::ConfigError(format!(
+                    "pickups.{}.duration must be positive",
+                    name
+                )));
+            }
+        }
+
+        if self.start_player_life <= 0 {
+            return Err(GameError::ConfigError(format!(
+                "start_player_life must be positive, got {}",
+                self.start_player_life
+            )));
+        }
+
+        match self.levels.first() {
+            Some(level) if level.start == 0 => (),
+            _ => {
+                return Err(GameError::ConfigError(
+                    "the first level must start at 0".to_owned(),
+                ))
+            }
+        }
+
+        for (i, level) in self.levels.iter().enumerate() {
+            level.validate(&format!("levels[{}]", i))?;
+
+            if i > 0 && level.start <= self.levels[i - 1].start {
+                return Err(GameError::ConfigError(format!(
+                    "levels[{}] must start after the previous level",
+                    i
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Describes the entries whose value differ in other
+    pub fn changes(&self, other: &Config) -> Vec<String> {
+        match (Value::try_from(self), Value::try_from(other)) {
+            (Ok(Value::Table(old)), Ok(Value::Table(new))) => new.iter()
+                .filter(|&(name, value)| old.get(name) != Some(value))
+                .map(|(name, value)| format!("{}: {} -> {}", name, old[name], value))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // Lowest position of a player's center, when standing on the ground
+    pub fn max_y(&self) -> f32 {
+        HEIGHT - self.ground_height - self.radius
+    }
+}
+
+// Overwrites the entries of base found in other, tables are merged entry by entry
+fn merge(base: &mut Value, other: Value) {
+    match (base, other) {
+        (&mut Value::Table(ref mut base), Value::Table(other)) => for (name, value) in other {
+            match base.entry(name) {
+                Entry::Occupied(mut entry) => merge(entry.get_mut(), value),
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        },
+        (base, other) => *base = other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tuning_keeps_the_current_values() {
+        let mut current = Config {
+            shield_drain: 0.02,
+            ..Config::default()
+        };
+        current.pickups.magnet.duration = 600;
+
+        let tuned = current
+            .parse_over("radius = 40.0\n[pickups.magnet]\nfrequency = 900\n")
+            .unwrap();
+        assert_eq!(tuned.radius, 40.0);
+        assert_eq!(tuned.pickups.magnet, PickupRule::new(900, 600));
+        assert_eq!(tuned.shield_drain, 0.02);
+        assert_eq!(tuned.pickups.shield, current.pickups.shield);
+
+        assert!(current.parse_over("radius = -1.0\n").is_err());
+        assert!(current.parse_over("unknown = 1\n").is_err());
+    }
+
+    #[test]
+    fn broken_player_physics_are_refused() {
+        let config = Config::default();
+        assert!(config.parse_over("jump_height = 0.0\n").is_err());
+        assert!(config.parse_over("jump_attenuation = -1.5\n").is_err());
+        assert!(config.parse_over(&format!("ground_height = {}\n", HEIGHT)).is_err());
+
+        let nan = Config {
+            tolerance: f32::NAN,
+            ..Config::default()
+        };
+        assert!(nan.validate().is_err());
+
+        let nan = Config {
+            fast_attenuation: f32::NAN,
+            ..Config::default()
+        };
+        assert!(nan.validate().is_err());
+    }
+}
@@ -1,21 +1,15 @@
 pub const WIDTH: f32 = 800.0;
 pub const HEIGHT: f32 = 600.0;
-pub const RADIUS: f32 = 32.0;
-pub const TOLERANCE: f32 = 2.0;
-pub const GROUND_HEIGHT: f32 = 100.0;
-
-pub const JUMP_HEIGHT: f32 = 25.0;
-pub const JUMP_ATTENUATION: f32 = 1.5;
-pub const FAST_ATTENUATION: f32 = 3.0;
-pub const PLAYER_SPEED: f32 = 10.0;
-pub const PLAYER_DAMPING: f32 = 2.0;
-pub const FLYING_DAMPING: f32 = 1.0;
 
 pub const UI_MARGIN: f32 = 10.0;
 
-pub const START_PLAYER_LIFE: i32 = 3;
 pub const LIFE_IMAGE_SIZE: f32 = 32.0;
 
-pub const SPAWN_FREQUENCY: u32 = 10;
+// The shield meter is drawn under the lifes
+pub const SHIELD_BAR_WIDTH: f32 = LIFE_IMAGE_SIZE * 3.0 + UI_MARGIN * 2.0;
+pub const SHIELD_BAR_HEIGHT: f32 = 4.0;
+
+pub const UPDATES_PER_SECOND: u32 = 60;
 
-pub const MAX_Y: f32 = HEIGHT - GROUND_HEIGHT - RADIUS;
+pub const PLAYBACK_SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+pub const DEFAULT_PLAYBACK_SPEED: usize = 2;
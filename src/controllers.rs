@@ -0,0 +1,87 @@
+use ggez::{Context, GameError, GameResult};
+use sdl2::GameControllerSubsystem;
+use sdl2::controller::GameController;
+use simulation::ControllerId;
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use throttle::Throttle;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ControllerEvent {
+    Connected(ControllerId),
+    Disconnected(ControllerId),
+}
+
+// Keeps every connected controller open, so that its events are received,
+// even when it is plugged after the start of the game
+pub struct Controllers {
+    subsystem: GameControllerSubsystem,
+    opened: BTreeMap<ControllerId, GameController>,
+    throttle: Throttle,
+}
+
+impl Controllers {
+    pub fn new(ctx: &Context) -> GameResult<Controllers> {
+        let mut controllers = Controllers {
+            subsystem: ctx.sdl_context.game_controller().map_err(GameError::UnknownError)?,
+            opened: BTreeMap::new(),
+            throttle: Throttle::new(),
+        };
+
+        // the controllers plugged before the start are not new
+        controllers.poll();
+        Ok(controllers)
+    }
+
+    // Returns the controllers connected or disconnected since the last call,
+    // the errors are only logged so that no disconnection is ever lost
+    pub fn poll(&mut self) -> Vec<ControllerEvent> {
+        if !self.throttle.ready() {
+            return Vec::new();
+        }
+
+        let mut events = Vec::new();
+
+        let disconnected: Vec<_> = self.opened
+            .iter()
+            .filter(|&(_, controller)| !controller.attached())
+            .map(|(&id, _)| id)
+            .collect();
+        for id in disconnected {
+            info!("Controller {} disconnected", id);
+            self.opened.remove(&id);
+            events.push(ControllerEvent::Disconnected(id));
+        }
+
+        let count = match self.subsystem.num_joysticks() {
+            Ok(count) => count,
+            Err(e) => {
+                error!("Failed to count the controllers: {}", e);
+                return events;
+            }
+        };
+        for index in 0..count {
+            if !self.subsystem.is_game_controller(index) {
+                continue;
+            }
+
+            // an already opened controller is only opened once more,
+            // opening one can fail while it is being plugged in
+            let controller = match self.subsystem.open(index) {
+                Ok(controller) => controller,
+                Err(e) => {
+                    error!("Failed to open controller {}: {}", index, e);
+                    continue;
+                }
+            };
+            let id = controller.instance_id();
+            if let Entry::Vacant(entry) = self.opened.entry(id) {
+                info!("Controller {} connected: {}", id, controller.name());
+                entry.insert(controller);
+                events.push(ControllerEvent::Connected(id));
+            }
+        }
+
+        events
+    }
+}
@@ -1,78 +1,391 @@
 use actions::{Action, GameAction, MoveDirection, PlayerAction};
-use baddies::Baddie;
+use controllers::{ControllerEvent, Controllers};
 use constants::*;
 use ggez::{graphics, timer, Context, GameResult};
 use ggez::event::{Axis, Button, EventHandler, Keycode, Mod, MouseButton, MouseState};
-use ggez::graphics::Point2;
-use player::PlayerController;
+use input::{Analog, Binding, Bindings, GameInput, PlayerInput};
+use replay::{Playback, Replay};
 use resources::Resources;
+use save::{self, QUICKSAVE_PATH};
+use simulation::{ControllerId, PlayerId, Simulation};
 use std::collections::HashMap;
-use std::iter;
-
-pub type ControllerId = i32;
-
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
-pub enum PlayerId {
-    Arrows,
-    ZQSD,
-    Controller(ControllerId),
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use tuning::TuningWatcher;
+
+// State of a replay being watched
+struct PlaybackState {
+    playback: Playback,
+    speed: usize,
+    paused: bool,
+    pending_ticks: f32,
 }
 
-impl From<ControllerId> for PlayerId {
-    fn from(id: ControllerId) -> Self {
-        PlayerId::Controller(id)
+impl PlaybackState {
+    fn speed(&self) -> f32 {
+        PLAYBACK_SPEEDS[self.speed]
     }
 }
 
+// ggez adapter around the simulation:
+// translates input events into actions and draws the current state.
 pub struct MainState {
-    players: HashMap<PlayerId, PlayerController>,
-    actions: Vec<Action>,
-    baddies: Vec<Baddie>,
+    simulation: Simulation,
     resources: Resources,
-    paused: bool,
+    seed_text: graphics::Text,
+    bindings: Bindings,
     input_stack: HashMap<(MoveDirection, PlayerId), u32>,
-    timer: u32,
+    // last position of each controller axis, only changes become actions
+    analog: HashMap<(ControllerId, Axis), Analog>,
+    recording: Option<(Replay, PathBuf)>,
+    playback: Option<PlaybackState>,
+    tuning: Option<TuningWatcher>,
+    controllers: Option<Controllers>,
+    pause_on_focus_loss: bool,
+    // visible area, in arena coordinates
+    screen: graphics::Rect,
 }
 
 impl MainState {
-    pub fn new(ctx: &mut Context) -> GameResult<MainState> {
-        let s = MainState {
-            players: HashMap::new(),
-            actions: Vec::new(),
-            baddies: Vec::new(),
-            resources: Resources::new(ctx)?,
+    pub fn new(
+        ctx: &mut Context,
+        simulation: Simulation,
+        bindings: Bindings,
+    ) -> GameResult<MainState> {
+        let mut s = MainState::with_simulation(ctx, simulation, bindings)?;
+        s.controllers = Some(Controllers::new(ctx)?);
+        Ok(s)
+    }
+
+    // Plays a replay instead of listening to the players
+    pub fn from_replay(ctx: &mut Context, replay: Replay) -> GameResult<MainState> {
+        let playback = Playback::new(replay);
+        // the players are not listened to
+        let mut s = MainState::with_simulation(ctx, playback.simulation()?, Bindings::default())?;
+        s.playback = Some(PlaybackState {
+            playback,
+            speed: DEFAULT_PLAYBACK_SPEED,
             paused: false,
+            pending_ticks: 0.0,
+        });
+        Ok(s)
+    }
+
+    fn with_simulation(
+        ctx: &mut Context,
+        simulation: Simulation,
+        bindings: Bindings,
+    ) -> GameResult<MainState> {
+        let resources = Resources::new(ctx)?;
+        let seed_text = seed_text(ctx, &resources, &simulation)?;
+
+        let (width, height) = graphics::get_size(ctx);
+        let screen = letterbox(width, height);
+        graphics::set_screen_coordinates(ctx, screen)?;
+
+        let s = MainState {
+            simulation,
+            resources,
+            seed_text,
+            bindings,
             input_stack: HashMap::with_capacity(2),
-            timer: 0,
+            analog: HashMap::new(),
+            recording: None,
+            playback: None,
+            tuning: None,
+            controllers: None,
+            pause_on_focus_loss: true,
+            screen,
         };
         Ok(s)
     }
 
+    // Records every action until the end of the session,
+    // the replay is written to path when the game quits
+    pub fn start_recording(&mut self, path: PathBuf) {
+        let replay = Replay::new(
+            self.simulation.seed(),
+            self.simulation.config().clone(),
+            self.simulation.waves().waves().to_vec(),
+        );
+        self.recording = Some((replay, path));
+    }
+
+    // Keeps the game running when the window loses focus
+    pub fn set_pause_on_focus_loss(&mut self, enabled: bool) {
+        self.pause_on_focus_loss = enabled;
+    }
+
+    // Applies the tuning file now, then each of its changes to the running game
+    pub fn watch_tuning(&mut self, path: PathBuf) -> GameResult<()> {
+        let mut tuning = TuningWatcher::new(path);
+        if let Some(config) = tuning.poll(self.simulation.config())? {
+            self.simulation.set_config(config)?;
+        }
+        self.tuning = Some(tuning);
+        Ok(())
+    }
+
+    fn update_tuning(&mut self) -> GameResult<()> {
+        let current = self.simulation.config();
+        let config = match self.tuning.as_mut().map(|tuning| tuning.poll(current)) {
+            Some(Ok(Some(config))) => config,
+            Some(Err(e)) => {
+                error!("Failed to reload the tuning file: {}", e);
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+
+        // the replay can't be played with a configuration changing over time
+        if self.recording.is_some() {
+            warn!("Tuning changed, the recording stops here");
+            self.save_recording()?;
+        }
+
+        self.simulation.set_config(config)
+    }
+
+    fn update_controllers(&mut self) -> GameResult<()> {
+        let events = match self.controllers.as_mut() {
+            Some(controllers) => controllers.poll(),
+            None => return Ok(()),
+        };
+
+        // players waiting for a controller, the first one gets the next plugged in
+        let mut lost: Vec<_> = self.simulation
+            .players()
+            .iter()
+            .filter(|&(_, p)| !p.is_connected())
+            .map(|(&id, _)| id)
+            .collect();
+
+        for event in events {
+            match event {
+                ControllerEvent::Disconnected(instance_id) => {
+                    let id = PlayerId::from(instance_id);
+                    self.analog.retain(|&(controller, _), _| controller != instance_id);
+
+                    if self.simulation.has_player(id) {
+                        warn!("{:?} lost their controller, the game is paused", id);
+                        self.release_inputs(id);
+                        self.add_action(GameAction::Disconnect(id));
+                        lost.push(id);
+                    }
+                }
+                ControllerEvent::Connected(instance_id) => if !lost.is_empty() {
+                    let id = lost.remove(0);
+                    info!("{:?} now plays with controller {}", id, instance_id);
+                    self.add_action(GameAction::Reconnect(id, instance_id.into()));
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn focus_lost(&mut self) {
+        // the keys released while the window is not focused would stay held
+        let ids: Vec<_> = self.simulation.players().keys().cloned().collect();
+        for id in ids {
+            self.release_inputs(id);
+        }
+
+        if self.pause_on_focus_loss && !self.simulation.players().is_empty() {
+            self.add_action(GameAction::Suspend);
+        }
+    }
+
+    // Releases every key or button held by this player
+    fn release_inputs(&mut self, id: PlayerId) {
+        self.input_stack.retain(|&(_, player), _| player != id);
+        if let PlayerId::Controller(instance_id) = id {
+            self.analog.retain(|&(controller, _), _| controller != instance_id);
+        }
+        self.add_action((PlayerAction::Move(None), id));
+        self.add_action((PlayerAction::Steer(0.0), id));
+        self.add_action((PlayerAction::Jump(false), id));
+        self.add_action((PlayerAction::Dump(false), id));
+        self.add_action((PlayerAction::Shield(false), id));
+    }
+
+    fn save_recording(&mut self) -> GameResult<()> {
+        if let Some((mut replay, path)) = self.recording.take() {
+            replay.finish(&self.simulation);
+            replay.save(BufWriter::new(File::create(&path)?))?;
+            info!("Replay saved to {}", path.display());
+        }
+
+        Ok(())
+    }
+
+    fn quick_save(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let file = ctx.filesystem.create(QUICKSAVE_PATH)?;
+        save::save(&self.simulation, BufWriter::new(file))?;
+        info!("Game saved to {}", QUICKSAVE_PATH);
+        Ok(())
+    }
+
+    fn quick_load(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let file = ctx.filesystem.open(QUICKSAVE_PATH)?;
+        let simulation = save::load(BufReader::new(file))?;
+
+        // the actions recorded so far can't be replayed on top of a loaded game
+        self.save_recording()?;
+
+        self.seed_text = seed_text(ctx, &self.resources, &simulation)?;
+        self.simulation = simulation;
+        self.input_stack.clear();
+        self.analog.clear();
+
+        // the loaded players still hold the keys held when the game was saved
+        let ids: Vec<_> = self.simulation.players().keys().cloned().collect();
+        for id in ids {
+            self.release_inputs(id);
+        }
+        info!("Game loaded from {}", QUICKSAVE_PATH);
+        Ok(())
+    }
+
+    pub fn spawn_player(&mut self, id: PlayerId) {
+        self.add_action(GameAction::Spawn(id));
+    }
+
     fn add_action<A: Into<Action>>(&mut self, action: A) {
-        self.actions.push(action.into());
-    }
-
-    fn process_actions(&mut self, ctx: &mut Context) -> GameResult<()> {
-        use self::GameAction::*;
-
-        for action in &self.actions {
-            match *action {
-                Action::Game(Pause) => self.paused = !self.paused,
-                Action::Game(Quit) => ctx.quit()?,
-                Action::Game(Spawn(id)) => {
-                    let index = self.players.len() as u8;
-                    let controller = self.players
-                        .entry(id)
-                        .or_insert_with(|| PlayerController::new(index));
-                    controller
-                        .process_action(PlayerAction::Spawn(Point2::new(WIDTH / 2.0, MAX_Y)))?;
+        let action = action.into();
+
+        if let Some((ref mut replay, _)) = self.recording {
+            replay.record(self.simulation.tick(), action);
+        }
+
+        self.simulation.add_action(action);
+    }
+
+    fn playback_key_down(&mut self, ctx: &mut Context, keycode: Keycode, repeat: bool) {
+        let state = match self.playback {
+            Some(ref mut state) => state,
+            None => return,
+        };
+
+        match keycode {
+            Keycode::Escape => ctx.quit().expect("Failed to quit"),
+            Keycode::Space if !repeat => state.paused = !state.paused,
+            Keycode::Up if !repeat => state.speed = (state.speed + 1).min(PLAYBACK_SPEEDS.len() - 1),
+            Keycode::Down if !repeat => state.speed = state.speed.saturating_sub(1),
+            // frame stepping
+            Keycode::Right if state.paused => state.pending_ticks += 1.0,
+            _ => (),
+        }
+    }
+
+    fn game_input(&mut self, ctx: &mut Context, input: GameInput) {
+        match input {
+            GameInput::Pause => self.pause_or_restart(),
+            GameInput::Quit => self.add_action(GameAction::Quit),
+            GameInput::Restart => self.add_action(GameAction::Restart),
+            GameInput::QuickSave => {
+                if let Err(e) = self.quick_save(ctx) {
+                    error!("Quick save failed: {}", e);
+                }
+            }
+            GameInput::QuickLoad => {
+                if let Err(e) = self.quick_load(ctx) {
+                    error!("Quick load failed: {}", e);
                 }
-                Action::Player(_, _) if self.paused => (),
-                Action::Player(a, id) => self.players.get_mut(&id).unwrap().process_action(a)?,
             }
         }
+    }
+
+    // Translates a key or a button pressed (or released) by a player into actions
+    fn player_input(&mut self, input: PlayerInput, id: PlayerId, pressed: bool) {
+        let with_player = self.simulation.has_player(id);
+
+        match (input, pressed) {
+            (PlayerInput::Left, true) if with_player => self.stack_input(MoveDirection::Left, id),
+            (PlayerInput::Left, false) if with_player => {
+                self.unstack_input(MoveDirection::Left, id)
+            }
+            (PlayerInput::Right, true) if with_player => {
+                self.stack_input(MoveDirection::Right, id)
+            }
+            (PlayerInput::Right, false) if with_player => {
+                self.unstack_input(MoveDirection::Right, id)
+            }
+            (PlayerInput::Down, dump) if with_player => {
+                self.add_action((PlayerAction::Dump(dump), id))
+            }
+            (PlayerInput::Jump, jump) if with_player => {
+                self.add_action((PlayerAction::Jump(jump), id))
+            }
+            (PlayerInput::Shield, shield) if with_player => {
+                self.add_action((PlayerAction::Shield(shield), id))
+            }
+            (PlayerInput::Spawn, true) => self.add_action(GameAction::Spawn(id)),
+            (PlayerInput::Leave, true) if with_player => {
+                self.release_inputs(id);
+                self.add_action(GameAction::Leave(id));
+            }
+            (PlayerInput::Pause, true) => self.pause_or_restart(),
+            _ => (),
+        }
+    }
+
+    // Once the game is over, pausing starts a new one
+    fn pause_or_restart(&mut self) {
+        if self.simulation.is_over() {
+            self.add_action(GameAction::Restart);
+        } else {
+            self.add_action(GameAction::Pause);
+        }
+    }
+
+    // Final scores and stats of every player, over the arena
+    fn draw_results(&self, ctx: &mut Context) -> GameResult<()> {
+        use self::graphics::*;
+
+        set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.8))?;
+        rectangle(ctx, DrawMode::Fill, Rect::new(0.0, 0.0, WIDTH, HEIGHT))?;
+
+        let mut lines = Vec::new();
+        for p in self.simulation.players().values() {
+            if let Some(stats) = p.stats() {
+                let seconds = stats.survived / UPDATES_PER_SECOND;
+                lines.push(format!(
+                    "PLAYER {}: {} POINTS IN {}:{:02}{}",
+                    p.index() + 1,
+                    stats.score,
+                    seconds / 60,
+                    seconds % 60,
+                    if p.is_dead() { "" } else { ", ALIVE" }
+                ));
+                lines.push(format!(
+                    "{} CAPTURES, {} HITS, BEST COMBO {}, {} PICKUPS",
+                    stats.captures, stats.hits, stats.best_combo, stats.pickups
+                ));
+            }
+        }
+
+        let mut texts = vec![self.resources.game_over.clone()];
+        for line in &lines {
+            texts.push(Text::new(ctx, line, &self.resources.font)?);
+        }
+        texts.push(self.resources.restart.clone());
+
+        // centered, with a blank line around the stats
+        let line_height = self.resources.game_over.height() as f32 + UI_MARGIN;
+        let height = (texts.len() + 2) as f32 * line_height;
+        let mut y = (HEIGHT - height) / 2.0;
+
+        set_color(ctx, Color::from_rgb(255, 255, 255))?;
+        for (i, text) in texts.iter().enumerate() {
+            if i == 1 || i == texts.len() - 1 {
+                y += line_height;
+            }
+            draw(ctx, text, Point2::new((WIDTH - text.width() as f32) / 2.0, y), 0.0)?;
+            y += line_height;
+        }
 
-        self.actions.clear();
         Ok(())
     }
 
@@ -118,66 +431,60 @@ impl MainState {
     }
 }
 
+// Screen coordinates showing the whole arena, centered,
+// whatever the aspect ratio of the window is
+fn letterbox(width: u32, height: u32) -> graphics::Rect {
+    let (width, height) = (width as f32, height as f32);
+    let scale = (width / WIDTH).min(height / HEIGHT);
+    let (w, h) = (width / scale, height / scale);
+
+    graphics::Rect::new((WIDTH - w) / 2.0, (HEIGHT - h) / 2.0, w, h)
+}
+
+fn seed_text(
+    ctx: &mut Context,
+    res: &Resources,
+    simulation: &Simulation,
+) -> GameResult<graphics::Text> {
+    graphics::Text::new(ctx, &format!("SEED: {}", simulation.seed()), &res.font)
+}
+
 impl EventHandler for MainState {
     /// Called upon each physics update to the game.
     /// This should be where the game's logic takes place.
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        self.process_actions(ctx)?;
-
-        if self.players.is_empty() || self.paused {
-            return Ok(());
-        }
-
-        // Update players
-        for p in self.players.values_mut() {
-            p.update(ctx)?;
-        }
-
-        let overlapping_players = {
-            let players_id = self.players.keys().cloned();
-            players_id
-                .clone()
-                .enumerate()
-                .flat_map(|(i, p1)| iter::repeat(p1).zip(players_id.clone().skip(i + 1)))
-                .filter(|&(p1, p2)| self.players[&p1].overlaps_player(self.players[&p2].body()))
-                .collect::<Vec<_>>()
-        };
-
-        for &(p1, p2) in &overlapping_players {
-            let player1 = self.players[&p1].body().unwrap();
-            let player2 = self.players[&p2].body().unwrap();
-            self.add_action((PlayerAction::Collides(player2.into()), p1));
-            self.add_action((PlayerAction::Collides(player1.into()), p2));
-        }
+        // The simulation runs at a fixed rate, whatever the frame rate is
+        if let Some(ref mut state) = self.playback {
+            while timer::check_update_time(ctx, UPDATES_PER_SECOND) {
+                if !state.paused {
+                    state.pending_ticks += state.speed();
+                }
+            }
 
-        // Update baddies
-        if self.timer % SPAWN_FREQUENCY == 0 {
-            self.baddies.push(Baddie::new());
-        }
+            while state.pending_ticks >= 1.0 && !state.playback.is_over() {
+                state.playback.step(&mut self.simulation)?;
+                state.pending_ticks -= 1.0;
 
-        self.baddies.retain(|b| b.body.y < HEIGHT);
+                if state.playback.is_over() {
+                    if let Err(e) = state.playback.check(&self.simulation) {
+                        warn!("{}", e);
+                    }
+                }
+            }
+        } else {
+            self.update_tuning()?;
+            self.update_controllers()?;
 
-        // used in place of drain_filter...
-        let mut i = 0;
-        while i != self.baddies.len() {
-            if let Some((&id, _)) = self.players
-                .iter()
-                .find(|&(_, p)| p.overlaps(&self.baddies[i].body))
-            {
-                let baddie = self.baddies.remove(i);
-                self.add_action((PlayerAction::Collides(baddie.into()), id));
-                break;
-            } else {
-                i += 1;
+            while timer::check_update_time(ctx, UPDATES_PER_SECOND) {
+                self.simulation.update()?;
             }
         }
 
-        for baddie in &mut self.baddies {
-            baddie.update(ctx)?;
+        if self.simulation.quit_requested() {
+            self.save_recording()?;
+            ctx.quit()?;
         }
 
-        self.timer += 1;
-
         Ok(())
     }
 
@@ -190,32 +497,128 @@ impl EventHandler for MainState {
 
         clear(ctx);
 
+        let playback_paused = self.playback
+            .as_ref()
+            .map_or(false, |state| state.paused || state.playback.is_over());
+
+        // interpolate between the last two updates, unless nothing is moving
+        let frozen = self.simulation.is_paused() || self.simulation.is_over()
+            || self.simulation.players().is_empty();
+        let alpha = if frozen || playback_paused {
+            1.0
+        } else {
+            let remaining = timer::duration_to_f64(timer::get_remaining_update_time(ctx));
+            (remaining * f64::from(UPDATES_PER_SECOND)).min(1.0) as f32
+        };
+
         // draw baddies
-        for baddie in &self.baddies {
-            baddie.draw(&self.resources, ctx)?;
+        for baddie in self.simulation.baddies() {
+            baddie.draw(&self.resources, alpha, ctx)?;
+        }
+
+        // draw pickups
+        for pickup in self.simulation.pickups() {
+            pickup.draw(&self.resources, alpha, ctx)?;
         }
 
         // draw player
-        for p in self.players.values() {
-            p.draw(&self.resources, ctx)?;
+        for p in self.simulation.players().values() {
+            p.draw(&self.resources, self.simulation.config(), alpha, ctx)?;
         }
 
         // draw ground
+        let ground_height = self.simulation.config().ground_height;
         set_color(ctx, Color::from_rgb(0, 0, 0))?;
         rectangle(
             ctx,
             DrawMode::Fill,
-            Rect::new(0.0, HEIGHT - GROUND_HEIGHT, WIDTH, GROUND_HEIGHT),
+            Rect::new(0.0, HEIGHT - ground_height, WIDTH, ground_height),
         )?;
 
-        for p in self.players.values() {
-            p.draw_ui(&self.resources, self.players.len(), ctx)?;
+        // hide what is outside of the arena
+        let screen = self.screen;
+        let bars = [
+            Rect::new(screen.x, screen.y, -screen.x, screen.h),
+            Rect::new(WIDTH, screen.y, screen.x + screen.w - WIDTH, screen.h),
+            Rect::new(screen.x, screen.y, screen.w, -screen.y),
+            Rect::new(screen.x, HEIGHT, screen.w, screen.y + screen.h - HEIGHT),
+        ];
+        for bar in bars.iter().filter(|bar| bar.w > 0.0 && bar.h > 0.0) {
+            rectangle(ctx, DrawMode::Fill, *bar)?;
+        }
+
+        // draw seed
+        set_color(ctx, Color::from_rgb(255, 255, 255))?;
+        let Rect { h: sh, .. } = self.seed_text.get_dimensions();
+        draw(
+            ctx,
+            &self.seed_text,
+            Point2::new(screen.x + UI_MARGIN, screen.y + screen.h - UI_MARGIN - sh),
+            0.0,
+        )?;
+
+        // draw level, or wave when they are scripted
+        let progress = match self.simulation.waves().wave() {
+            Some(wave) => format!("WAVE {}", wave + 1),
+            None => format!("LEVEL {}", self.simulation.level() + 1),
+        };
+        let text = Text::new(ctx, &progress, &self.resources.font)?;
+        let Rect { w: tw, h: th, .. } = text.get_dimensions();
+        draw(
+            ctx,
+            &text,
+            Point2::new(
+                screen.x + (screen.w - tw) / 2.0,
+                screen.y + screen.h - UI_MARGIN - th,
+            ),
+            0.0,
+        )?;
+
+        // draw playback status
+        if let Some(ref state) = self.playback {
+            let status = if state.playback.is_over() {
+                "REPLAY OVER".to_owned()
+            } else if state.paused {
+                "REPLAY PAUSED".to_owned()
+            } else {
+                format!("REPLAY x{}", state.speed())
+            };
+            let text = Text::new(ctx, &status, &self.resources.font)?;
+            let Rect { w: tw, h: th, .. } = text.get_dimensions();
+            draw(
+                ctx,
+                &text,
+                Point2::new(
+                    screen.x + screen.w - UI_MARGIN - tw,
+                    screen.y + screen.h - UI_MARGIN - th,
+                ),
+                0.0,
+            )?;
+        }
+
+        let nb_slots = self.simulation
+            .players()
+            .values()
+            .map(|p| usize::from(p.index()) + 1)
+            .max()
+            .unwrap_or(0);
+        for p in self.simulation.players().values() {
+            p.draw_ui(
+                &self.resources,
+                self.simulation.config(),
+                screen,
+                nb_slots,
+                ctx,
+            )?;
         }
 
         // draw message
-        if self.players.is_empty() || self.paused {
+        let waiting = self.simulation.players().is_empty();
+        if self.simulation.is_over() {
+            self.draw_results(ctx)?;
+        } else if waiting || self.simulation.is_paused() {
             set_color(ctx, Color::from_rgb(255, 255, 255))?;
-            let text = if self.players.is_empty() {
+            let text = if waiting {
                 &self.resources.waiting
             } else {
                 &self.resources.pause
@@ -225,7 +628,7 @@ impl EventHandler for MainState {
             draw(
                 ctx,
                 text,
-                Point2::new((WIDTH - tw) / 2.0, (HEIGHT - GROUND_HEIGHT - th) / 2.0),
+                Point2::new((WIDTH - tw) / 2.0, (HEIGHT - ground_height - th) / 2.0),
                 0.0,
             )?;
         }
@@ -279,7 +682,7 @@ impl EventHandler for MainState {
     }
 
     /// A keyboard button was pressed.
-    fn key_down_event(&mut self, _ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) {
+    fn key_down_event(&mut self, ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) {
         debug!(
             "key_down_event - {:?} ({:?}): {}",
             keycode,
@@ -287,35 +690,19 @@ impl EventHandler for MainState {
             if repeat { "repeated" } else { "first" }
         );
 
-        use self::Keycode::*;
+        if self.playback.is_some() {
+            self.playback_key_down(ctx, keycode, repeat);
+            return;
+        }
 
         if repeat {
             return;
         }
 
-        let with_arrows = self.players.contains_key(&PlayerId::Arrows);
-        let with_zqsd = self.players.contains_key(&PlayerId::ZQSD);
-
-        match keycode {
-            Escape => self.add_action(GameAction::Quit),
-            Space => self.add_action(GameAction::Pause),
-
-            // ARROWS
-            Left if with_arrows => self.stack_input(MoveDirection::Left, PlayerId::Arrows),
-            Right if with_arrows => self.stack_input(MoveDirection::Right, PlayerId::Arrows),
-            Down if with_arrows => self.add_action((PlayerAction::Dump(true), PlayerId::Arrows)),
-            Up if with_arrows => self.add_action((PlayerAction::Jump, PlayerId::Arrows)),
-            RCtrl if with_arrows => self.add_action((PlayerAction::Shield(true), PlayerId::Arrows)),
-            Return => self.add_action(GameAction::Spawn(PlayerId::Arrows)),
-
-            // ZQSD
-            Q if with_zqsd => self.stack_input(MoveDirection::Left, PlayerId::ZQSD),
-            D if with_zqsd => self.stack_input(MoveDirection::Right, PlayerId::ZQSD),
-            S if with_zqsd => self.add_action((PlayerAction::Dump(true), PlayerId::ZQSD)),
-            Z if with_zqsd => self.add_action((PlayerAction::Jump, PlayerId::ZQSD)),
-            LShift if with_zqsd => self.add_action((PlayerAction::Shield(true), PlayerId::ZQSD)),
-            LCtrl => self.add_action(GameAction::Spawn(PlayerId::ZQSD)),
-            _ => (),
+        match self.bindings.key(keycode) {
+            Some(Binding::Game(input)) => self.game_input(ctx, input),
+            Some(Binding::Player(input, id)) => self.player_input(input, id, true),
+            None => (),
         }
     }
 
@@ -328,32 +715,8 @@ impl EventHandler for MainState {
             if repeat { "repeated" } else { "first" }
         );
 
-        let with_arrows = self.players.contains_key(&PlayerId::Arrows);
-        let with_zqsd = self.players.contains_key(&PlayerId::ZQSD);
-
-        match keycode {
-            // ARROWS
-            Keycode::Left if with_arrows => {
-                self.unstack_input(MoveDirection::Left, PlayerId::Arrows)
-            }
-            Keycode::Right if with_arrows => {
-                self.unstack_input(MoveDirection::Right, PlayerId::Arrows)
-            }
-            Keycode::Down if with_arrows => {
-                self.add_action((PlayerAction::Dump(false), PlayerId::Arrows))
-            }
-            Keycode::RCtrl if with_arrows => {
-                self.add_action((PlayerAction::Shield(false), PlayerId::Arrows))
-            }
-
-            // ZQSD
-            Keycode::Q if with_zqsd => self.unstack_input(MoveDirection::Left, PlayerId::ZQSD),
-            Keycode::D if with_zqsd => self.unstack_input(MoveDirection::Right, PlayerId::ZQSD),
-            Keycode::S if with_zqsd => self.add_action((PlayerAction::Dump(false), PlayerId::ZQSD)),
-            Keycode::LShift if with_zqsd => {
-                self.add_action((PlayerAction::Shield(false), PlayerId::ZQSD))
-            }
-            _ => (),
+        if let Some(Binding::Player(input, id)) = self.bindings.key(keycode) {
+            self.player_input(input, id, false);
         }
     }
 
@@ -366,21 +729,8 @@ impl EventHandler for MainState {
     ) {
         debug!("controller_button_down_event - {:?} ({})", btn, instance_id);
 
-        use self::MoveDirection::*;
-
-        let with_player = self.players.contains_key(&instance_id.into());
-
-        match (btn, with_player) {
-            (Button::DPadLeft, true) => self.stack_input(Left, instance_id.into()),
-            (Button::DPadRight, true) => self.stack_input(Right, instance_id.into()),
-            (Button::DPadDown, true) => {
-                self.add_action((PlayerAction::Dump(true), instance_id.into()))
-            }
-            (Button::B, true) => self.add_action((PlayerAction::Jump, instance_id.into())),
-            (Button::A, true) => self.add_action((PlayerAction::Shield(true), instance_id.into())),
-            (Button::Start, _) => self.add_action(GameAction::Pause),
-            (Button::Back, _) => self.add_action(GameAction::Spawn(instance_id.into())),
-            _ => (),
+        if let Some(input) = self.bindings.button(btn) {
+            self.player_input(input, instance_id.into(), true);
         }
     }
     /// A controller button was released.
@@ -392,18 +742,8 @@ impl EventHandler for MainState {
     ) {
         debug!("controller_button_up_event - {:?} ({})", btn, instance_id);
 
-        use self::MoveDirection::*;
-
-        let with_player = self.players.contains_key(&instance_id.into());
-
-        match (btn, with_player) {
-            (Button::DPadLeft, true) => self.unstack_input(Left, instance_id.into()),
-            (Button::DPadRight, true) => self.unstack_input(Right, instance_id.into()),
-            (Button::DPadDown, true) => {
-                self.add_action((PlayerAction::Dump(false), instance_id.into()))
-            }
-            (Button::A, true) => self.add_action((PlayerAction::Shield(false), instance_id.into())),
-            _ => (),
+        if let Some(input) = self.bindings.button(btn) {
+            self.player_input(input, instance_id.into(), false);
         }
     }
     /// A controller axis moved.
@@ -418,10 +758,53 @@ impl EventHandler for MainState {
             "controller_axis_event - {:?}[{}] ({})",
             axis, value, instance_id
         );
+
+        let analog = match self.bindings.axis(axis, value) {
+            Some(analog) => analog,
+            None => return,
+        };
+        let id = PlayerId::from(instance_id);
+
+        if let Analog::Move(_) = analog {
+            if !self.simulation.has_player(id) {
+                return;
+            }
+        }
+        if self.analog.insert((instance_id, axis), analog) == Some(analog) {
+            return;
+        }
+
+        match analog {
+            Analog::Move(steering) => self.add_action((PlayerAction::Steer(steering), id)),
+            Analog::Button(input, pressed) => self.player_input(input, id, pressed),
+        }
+    }
+
+    /// Called upon a quit event, returning true cancels it.
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        if let Err(e) = self.save_recording() {
+            error!("Failed to save the replay: {}", e);
+        }
+
+        false
+    }
+
+    /// Called when the window is resized.
+    fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        debug!("resize_event - {}x{}", width, height);
+
+        self.screen = letterbox(width, height);
+        if let Err(e) = graphics::set_screen_coordinates(ctx, self.screen) {
+            error!("Failed to resize the screen: {}", e);
+        }
     }
 
     /// Called when the window is shown or hidden.
     fn focus_event(&mut self, _ctx: &mut Context, gained: bool) {
         debug!("focus_event - {}", if gained { "gained" } else { "loose" });
+
+        if !gained && self.playback.is_none() {
+            self.focus_lost();
+        }
     }
 }
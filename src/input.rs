@@ -0,0 +1,318 @@
+use ggez::{Context, GameError, GameResult};
+use ggez::event::{Axis, Button, Keycode};
+use simulation::{KeyboardId, PlayerId};
+use std::collections::HashMap;
+use std::io::Read;
+use toml;
+
+pub const BINDINGS_PATH: &str = "/bindings.toml";
+
+// Used when there is no BINDINGS_PATH
+const DEFAULT_BINDINGS: &str = include_str!("../resources/bindings.toml");
+
+// Steering is rounded to 1/STEERING_STEPS, so that a stick barely moving
+// doesn't record a new action each time
+const STEERING_STEPS: f32 = 32.0;
+
+// What a player can do with a key or a button
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PlayerInput {
+    Left,
+    Right,
+    Down,
+    Jump,
+    Shield,
+    Spawn,
+    Leave,
+    Pause,
+}
+
+// Keys shared by every player
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GameInput {
+    Pause,
+    Quit,
+    Restart,
+    QuickSave,
+    QuickLoad,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Binding {
+    Game(GameInput),
+    Player(PlayerInput, PlayerId),
+}
+
+// What a controller axis is used for
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum AxisBinding {
+    Move,
+    // a trigger, pressed past the threshold
+    Button(PlayerInput),
+}
+
+// Position of a controller axis, once the dead zone is applied
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Analog {
+    // from -1.0 (full left) to 1.0 (full right), by steps of 1/STEERING_STEPS
+    Move(f32),
+    Button(PlayerInput, bool),
+}
+
+// Layout of BINDINGS_PATH, keys and buttons are named as in SDL
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BindingsFile {
+    #[serde(default)]
+    game: GameKeys,
+    #[serde(default)]
+    keyboard: Vec<KeyboardScheme>,
+    #[serde(default)]
+    controller: PlayerKeys,
+    #[serde(default)]
+    analog: AnalogKeys,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GameKeys {
+    pause: Option<String>,
+    quit: Option<String>,
+    restart: Option<String>,
+    quick_save: Option<String>,
+    quick_load: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyboardScheme {
+    name: String,
+    #[serde(default)]
+    keys: PlayerKeys,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PlayerKeys {
+    left: Option<String>,
+    right: Option<String>,
+    down: Option<String>,
+    jump: Option<String>,
+    shield: Option<String>,
+    spawn: Option<String>,
+    leave: Option<String>,
+    pause: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AnalogKeys {
+    dead_zone: f32,
+    trigger_threshold: f32,
+    stick: Option<String>,
+    down: Option<String>,
+    shield: Option<String>,
+}
+
+impl Default for AnalogKeys {
+    fn default() -> Self {
+        AnalogKeys {
+            dead_zone: 0.2,
+            trigger_threshold: 0.5,
+            stick: None,
+            down: None,
+            shield: None,
+        }
+    }
+}
+
+impl GameKeys {
+    fn inputs(&self) -> Vec<(GameInput, &str)> {
+        let inputs = [
+            (GameInput::Pause, &self.pause),
+            (GameInput::Quit, &self.quit),
+            (GameInput::Restart, &self.restart),
+            (GameInput::QuickSave, &self.quick_save),
+            (GameInput::QuickLoad, &self.quick_load),
+        ];
+        inputs
+            .iter()
+            .filter_map(|&(input, name)| name.as_ref().map(|name| (input, name.as_str())))
+            .collect()
+    }
+}
+
+impl PlayerKeys {
+    fn inputs(&self) -> Vec<(PlayerInput, &str)> {
+        let inputs = [
+            (PlayerInput::Left, &self.left),
+            (PlayerInput::Right, &self.right),
+            (PlayerInput::Down, &self.down),
+            (PlayerInput::Jump, &self.jump),
+            (PlayerInput::Shield, &self.shield),
+            (PlayerInput::Spawn, &self.spawn),
+            (PlayerInput::Leave, &self.leave),
+            (PlayerInput::Pause, &self.pause),
+        ];
+        inputs
+            .iter()
+            .filter_map(|&(input, name)| name.as_ref().map(|name| (input, name.as_str())))
+            .collect()
+    }
+}
+
+// Translates keys and buttons into inputs.
+// Each keyboard scheme is a player, identified by its position in the file;
+// every controller shares the same scheme.
+#[derive(Default)]
+pub struct Bindings {
+    keyboards: Vec<String>,
+    keys: HashMap<Keycode, Binding>,
+    buttons: HashMap<Button, PlayerInput>,
+    axes: HashMap<Axis, AxisBinding>,
+    dead_zone: f32,
+    trigger_threshold: f32,
+}
+
+impl Bindings {
+    // Loads BINDINGS_PATH, or the default bindings if there is no such file
+    pub fn load(ctx: &mut Context) -> GameResult<Bindings> {
+        if !ctx.filesystem.exists(BINDINGS_PATH) {
+            info!("No {} found, using the default bindings", BINDINGS_PATH);
+            return Bindings::parse(DEFAULT_BINDINGS);
+        }
+
+        let mut content = String::new();
+        ctx.filesystem.open(BINDINGS_PATH)?.read_to_string(&mut content)?;
+        Bindings::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> GameResult<Bindings> {
+        let file: BindingsFile = toml::from_str(content).map_err(|e| invalid(e.to_string()))?;
+
+        let max_keyboards = usize::from(KeyboardId::max_value()) + 1;
+        if file.keyboard.len() > max_keyboards {
+            return Err(invalid(format!(
+                "at most {} keyboard schemes are supported",
+                max_keyboards
+            )));
+        }
+
+        let mut bindings = Bindings {
+            keyboards: Vec::with_capacity(file.keyboard.len()),
+            keys: HashMap::new(),
+            buttons: HashMap::new(),
+            axes: HashMap::new(),
+            dead_zone: file.analog.dead_zone,
+            trigger_threshold: file.analog.trigger_threshold,
+        };
+
+        for (input, name) in file.game.inputs() {
+            bindings.bind_key(name, Binding::Game(input))?;
+        }
+
+        for (index, scheme) in file.keyboard.iter().enumerate() {
+            // controllers are named after their id
+            if scheme.name.parse::<i64>().is_ok() {
+                return Err(invalid(format!(
+                    "keyboard scheme name '{}' is a number",
+                    scheme.name
+                )));
+            }
+            if bindings.keyboards.contains(&scheme.name) {
+                return Err(invalid(format!(
+                    "keyboard scheme '{}' is defined twice",
+                    scheme.name
+                )));
+            }
+
+            let id = PlayerId::Keyboard(index as KeyboardId);
+            for (input, name) in scheme.keys.inputs() {
+                bindings.bind_key(name, Binding::Player(input, id))?;
+            }
+            bindings.keyboards.push(scheme.name.clone());
+        }
+
+        for (input, name) in file.controller.inputs() {
+            let button = Button::from_string(name)
+                .ok_or_else(|| invalid(format!("unknown controller button '{}'", name)))?;
+            if bindings.buttons.insert(button, input).is_some() {
+                return Err(invalid(format!("button '{}' is bound twice", name)));
+            }
+        }
+
+        let analog = &file.analog;
+        if analog.dead_zone.is_nan() || analog.dead_zone < 0.0 || analog.dead_zone >= 1.0 {
+            return Err(invalid(String::from("dead_zone must be in [0, 1)")));
+        }
+        if analog.trigger_threshold.is_nan() || analog.trigger_threshold <= 0.0
+            || analog.trigger_threshold > 1.0
+        {
+            return Err(invalid(String::from("trigger_threshold must be in (0, 1]")));
+        }
+
+        let axes = [
+            (AxisBinding::Move, &analog.stick),
+            (AxisBinding::Button(PlayerInput::Down), &analog.down),
+            (AxisBinding::Button(PlayerInput::Shield), &analog.shield),
+        ];
+        for &(binding, name) in &axes {
+            if let Some(ref name) = *name {
+                let axis = Axis::from_string(name)
+                    .ok_or_else(|| invalid(format!("unknown controller axis '{}'", name)))?;
+                if bindings.axes.insert(axis, binding).is_some() {
+                    return Err(invalid(format!("axis '{}' is bound twice", name)));
+                }
+            }
+        }
+
+        Ok(bindings)
+    }
+
+    fn bind_key(&mut self, name: &str, binding: Binding) -> GameResult<()> {
+        let keycode =
+            Keycode::from_name(name).ok_or_else(|| invalid(format!("unknown key '{}'", name)))?;
+        if self.keys.insert(keycode, binding).is_some() {
+            return Err(invalid(format!("key '{}' is bound twice", name)));
+        }
+        Ok(())
+    }
+
+    pub fn key(&self, keycode: Keycode) -> Option<Binding> {
+        self.keys.get(&keycode).cloned()
+    }
+
+    pub fn button(&self, button: Button) -> Option<PlayerInput> {
+        self.buttons.get(&button).cloned()
+    }
+
+    pub fn axis(&self, axis: Axis, value: i16) -> Option<Analog> {
+        let value = (f32::from(value) / f32::from(i16::max_value())).max(-1.0);
+
+        self.axes.get(&axis).map(|binding| match *binding {
+            AxisBinding::Move => {
+                // rescaled, so that the speed still starts from zero
+                let magnitude = ((value.abs() - self.dead_zone) / (1.0 - self.dead_zone)).max(0.0);
+                let steps = (magnitude * STEERING_STEPS).round() / STEERING_STEPS;
+                Analog::Move(steps * value.signum())
+            }
+            AxisBinding::Button(input) => Analog::Button(input, value >= self.trigger_threshold),
+        })
+    }
+
+    // A keyboard scheme name, or a controller id
+    pub fn player_id(&self, name: &str) -> Option<PlayerId> {
+        match self.keyboards.iter().position(|keyboard| keyboard == name) {
+            Some(index) => Some(PlayerId::Keyboard(index as KeyboardId)),
+            None => name.parse().ok().map(PlayerId::Controller),
+        }
+    }
+
+    pub fn keyboards(&self) -> &[String] {
+        &self.keyboards
+    }
+}
+
+fn invalid(message: String) -> GameError {
+    GameError::ConfigError(format!("Invalid bindings: {}", message))
+}
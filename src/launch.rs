@@ -0,0 +1,116 @@
+use config::Config;
+use game::MainState;
+use ggez::{Context, GameError, GameResult};
+use input::Bindings;
+use replay::{Playback, Replay};
+use simulation::Simulation;
+use waves;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub enum Mode {
+    Play,
+    // Watch a replay
+    Replay(PathBuf),
+    // Play a replay without any window, as fast as possible
+    Headless(PathBuf),
+}
+
+// What to run, everything but the window and the logs
+pub struct Options {
+    pub mode: Mode,
+    pub seed: Option<u64>,
+    // keyboard scheme names or controller ids
+    pub players: Vec<String>,
+    pub record: Option<PathBuf>,
+    pub tuning: Option<PathBuf>,
+    pub focus_pause: bool,
+    pub waves: String,
+}
+
+pub fn load_replay(path: &Path) -> GameResult<Replay> {
+    info!("Playing replay {}", path.display());
+    let file = File::open(path).map_err(|e| {
+        GameError::ResourceLoadError(format!("Failed to open {}: {}", path.display(), e))
+    })?;
+    Replay::load(BufReader::new(file))
+}
+
+// The outcome of a replay played without any window
+pub struct HeadlessRun {
+    pub simulation: Simulation,
+    // fails when the scores diverged from the recorded session
+    pub check: GameResult<()>,
+}
+
+// Plays the replay without any window, as fast as possible
+pub fn headless(path: &Path) -> GameResult<HeadlessRun> {
+    let mut playback = Playback::new(load_replay(path)?);
+    let simulation = playback.run()?;
+    let check = playback.check(&simulation);
+
+    Ok(HeadlessRun { simulation, check })
+}
+
+// The state of the game to run in the window, once the resources are mounted
+pub fn launch(ctx: &mut Context, options: Options) -> GameResult<MainState> {
+    match options.mode {
+        Mode::Play => (),
+        Mode::Replay(ref path) => return MainState::from_replay(ctx, load_replay(path)?),
+        Mode::Headless(_) => {
+            return Err(GameError::UnknownError(
+                "A headless replay has no window, use launch::headless".to_owned(),
+            ))
+        }
+    }
+
+    // The seed can be forced to reproduce a previous run
+    let seed = options.seed.unwrap_or_else(|| {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System time is before UNIX EPOCH");
+        now.as_secs() ^ u64::from(now.subsec_nanos())
+    });
+    info!("Using seed {}", seed);
+
+    let config = Config::load(ctx)?;
+    let waves = waves::load(ctx, &options.waves)?;
+
+    let bindings = Bindings::load(ctx)?;
+
+    // players are named after the keyboard schemes of the bindings
+    let mut players = Vec::with_capacity(options.players.len());
+    for name in &options.players {
+        let id = bindings.player_id(name).ok_or_else(|| {
+            GameError::ConfigError(format!(
+                "Unknown player {}, expected {} or a controller id",
+                name,
+                bindings.keyboards().join(", ")
+            ))
+        })?;
+        players.push(id);
+    }
+
+    let simulation = Simulation::with_waves(seed, config, waves)?;
+    let mut state = MainState::new(ctx, simulation, bindings)?;
+    state.set_pause_on_focus_loss(options.focus_pause);
+
+    if let Some(tuning_path) = options.tuning {
+        info!("Watching tuning file {}", tuning_path.display());
+        state.watch_tuning(tuning_path)?;
+    }
+
+    if let Some(replay_path) = options.record {
+        info!("Recording replay to {}", replay_path.display());
+        state.start_recording(replay_path);
+    }
+
+    for id in players {
+        state.spawn_player(id);
+    }
+
+    Ok(state)
+}
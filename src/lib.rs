@@ -0,0 +1,39 @@
+extern crate ggez;
+#[macro_use]
+extern crate log;
+extern crate rand;
+extern crate sdl2;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+
+pub mod config;
+pub mod constants;
+mod serialization;
+pub mod rng;
+pub mod actions;
+pub mod player;
+pub mod baddies;
+pub mod pickups;
+pub mod resources;
+pub mod simulation;
+pub mod replay;
+pub mod save;
+mod throttle;
+pub mod tuning;
+pub mod waves;
+pub mod input;
+pub mod controllers;
+pub mod game;
+pub mod launch;
+
+pub use actions::{Action, GameAction, PlayerAction};
+pub use baddies::Baddie;
+pub use config::Config;
+pub use game::MainState;
+pub use input::Bindings;
+pub use player::{Player, PlayerController};
+pub use replay::{Playback, Replay};
+pub use simulation::{PlayerId, Simulation};
@@ -1,34 +1,64 @@
 #![windows_subsystem = "windows"]
 
+#[macro_use]
+extern crate clap;
 extern crate flexi_logger;
 extern crate ggez;
+extern crate ggez_dodger;
 #[macro_use]
 extern crate log;
-extern crate rand;
-#[macro_use]
-extern crate rand_derive;
 
-mod constants;
-mod actions;
-mod player;
-mod baddies;
-mod resources;
-mod game;
+mod cli;
 
-use game::MainState;
+use cli::Options;
 
-use std::{env, path};
+use ggez_dodger::Simulation;
+use ggez_dodger::launch::{self, Mode};
+
+use std::{env, path, process};
 
 use ggez::{conf, event, graphics, Context};
 
 use flexi_logger::Logger;
 
 pub fn main() {
-    Logger::with_env_or_str("ggez_dodger=warn")
-        .start()
+    let options = Options::from_args();
+
+    match options.log_level {
+        Some(ref level) => Logger::with_str(&format!("ggez_dodger={}", level)),
+        None => Logger::with_env_or_str("ggez_dodger=warn"),
+    }.start()
         .unwrap_or_else(|e| panic!("Logger initialization failed with {}", e));
 
-    let c = conf::Conf::new();
+    // Without a window, the replay is played as fast as possible
+    if let Mode::Headless(ref replay_path) = options.launch.mode {
+        match launch::headless(replay_path) {
+            Ok(run) => {
+                print_results(&run.simulation);
+                if let Err(e) = run.check {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to play the replay: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut c = conf::Conf::new();
+    c.window_setup.resizable = true;
+    if let Some(width) = options.width {
+        c.window_mode.width = width;
+    }
+    if let Some(height) = options.height {
+        c.window_mode.height = height;
+    }
+    if options.fullscreen {
+        c.window_mode.fullscreen_type = conf::FullscreenType::Desktop;
+    }
     let ctx = &mut Context::load_from_conf("dodger", "gobanos", c).unwrap();
 
     // We add the CARGO_MANIFEST_DIR/resources do the filesystems paths so
@@ -41,7 +71,21 @@ pub fn main() {
 
     info!("{}", graphics::get_renderer_info(ctx).unwrap());
 
-    let state = &mut MainState::new(ctx).unwrap();
-
+    let state = &mut launch::launch(ctx, options.launch).unwrap();
     event::run(ctx, state).unwrap();
 }
+
+// Final scores of a replay played without any window
+fn print_results(simulation: &Simulation) {
+    println!("Replay over after {} ticks", simulation.tick());
+    if simulation.is_over() {
+        println!("Game over");
+    }
+    for (id, player) in simulation.players() {
+        match (player.score(), player.stats()) {
+            (Some(score), _) => println!("{:?}: {}", id, score),
+            (None, Some(stats)) => println!("{:?}: dead with {}", id, stats.score),
+            (None, None) => println!("{:?}: dead", id),
+        }
+    }
+}
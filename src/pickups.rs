@@ -0,0 +1,203 @@
+use config::Config;
+use constants::*;
+use resources::Resources;
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, Point2, Rect, Vector2};
+use rand::Rng;
+use rand::distributions::{Range, Sample};
+
+// Bonuses falling among the baddies, caught by touching them
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PickupKind {
+    // one more life
+    ExtraLife,
+    // any baddie is captured, for a while
+    Wildcard,
+    // baddies fall slower, for a while
+    SlowMotion,
+    // the baddies the player can capture are drawn to it, for a while
+    Magnet,
+    // no baddie can hurt the player, for a while
+    Shield,
+}
+
+pub const PICKUPS: [PickupKind; 5] = [
+    PickupKind::ExtraLife,
+    PickupKind::Wildcard,
+    PickupKind::SlowMotion,
+    PickupKind::Magnet,
+    PickupKind::Shield,
+];
+
+impl PickupKind {
+    // Shown on the pickup itself
+    pub fn symbol(self) -> &'static str {
+        match self {
+            PickupKind::ExtraLife => "+",
+            PickupKind::Wildcard => "?",
+            PickupKind::SlowMotion => "S",
+            PickupKind::Magnet => "M",
+            PickupKind::Shield => "O",
+        }
+    }
+
+    // Shown in the HUD, while the effect lasts
+    pub fn name(self) -> &'static str {
+        match self {
+            PickupKind::ExtraLife => "LIFE",
+            PickupKind::Wildcard => "WILDCARD",
+            PickupKind::SlowMotion => "SLOW",
+            PickupKind::Magnet => "MAGNET",
+            PickupKind::Shield => "SHIELD",
+        }
+    }
+}
+
+impl Into<Color> for PickupKind {
+    fn into(self) -> Color {
+        match self {
+            PickupKind::ExtraLife => Color::from_rgb_u32(0xc0_20_30),
+            PickupKind::Wildcard => Color::from_rgb_u32(0x8e_44_ad),
+            PickupKind::SlowMotion => Color::from_rgb_u32(0x16_a0_85),
+            PickupKind::Magnet => Color::from_rgb_u32(0xd3_54_00),
+            PickupKind::Shield => Color::from_rgb_u32(0x7f_8c_8d),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pickup {
+    #[serde(with = "::serialization::rect")]
+    pub body: Rect,
+    pub kind: PickupKind,
+
+    #[serde(with = "::serialization::point2")]
+    last_position: Point2,
+}
+
+impl Pickup {
+    // Called once per tick, returns the pickups to spawn, if any
+    pub fn spawn<R: Rng>(rng: &mut R, config: &Config) -> Vec<Pickup> {
+        let mut pickups = Vec::new();
+        for &kind in &PICKUPS {
+            // a pickup spawns every frequency ticks, on average
+            let frequency = config.pickups.rule(kind).frequency;
+            if frequency > 0 && rng.gen_range(0, frequency) == 0 {
+                pickups.push(Pickup::new(rng, kind, config));
+            }
+        }
+        pickups
+    }
+
+    pub fn new<R: Rng>(rng: &mut R, kind: PickupKind, config: &Config) -> Pickup {
+        let size = config.pickup_size;
+        let x = Range::new(0.0, WIDTH - size).sample(rng);
+
+        Pickup {
+            body: Rect::new(x, -size, size, size),
+            kind,
+            last_position: Point2::new(x, -size),
+        }
+    }
+
+    pub fn update(&mut self, config: &Config) {
+        self.last_position = self.body.point();
+        self.body.translate(Vector2::new(0.0, config.pickup_speed));
+    }
+
+    // alpha is the progress between the previous update and the current one
+    pub fn draw(&self, res: &Resources, alpha: f32, ctx: &mut Context) -> GameResult<()> {
+        use self::graphics::*;
+
+        let Rect { w, h, .. } = self.body;
+        let dest = self.last_position + (self.body.point() - self.last_position) * alpha;
+
+        if self.kind == PickupKind::ExtraLife {
+            let Rect { w: iw, h: ih, .. } = res.life.get_dimensions();
+            let params = DrawParam {
+                dest,
+                scale: Point2::new(w / iw, h / ih),
+                ..Default::default()
+            };
+
+            set_color(ctx, Color::from_rgb(255, 255, 255))?;
+            return draw_ex(ctx, &res.life, params);
+        }
+
+        let center = dest + Vector2::new(w, h) / 2.0;
+        set_color(ctx, self.kind.into())?;
+        circle(ctx, DrawMode::Fill, center, w / 2.0, 0.1)?;
+
+        let symbol = &res.pickups[&self.kind];
+        let Rect { w: sw, h: sh, .. } = symbol.get_dimensions();
+        set_color(ctx, Color::from_rgb(255, 255, 255))?;
+        draw(
+            ctx,
+            symbol,
+            Point2::new(center.x - sw / 2.0, center.y - sh / 2.0),
+            0.0,
+        )
+    }
+}
+
+// Ticks left for each effect of the pickups, an extra life has none
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct Effects {
+    wildcard: u32,
+    slow_motion: u32,
+    magnet: u32,
+    shield: u32,
+}
+
+impl Effects {
+    fn timer(&mut self, kind: PickupKind) -> Option<&mut u32> {
+        match kind {
+            PickupKind::ExtraLife => None,
+            PickupKind::Wildcard => Some(&mut self.wildcard),
+            PickupKind::SlowMotion => Some(&mut self.slow_motion),
+            PickupKind::Magnet => Some(&mut self.magnet),
+            PickupKind::Shield => Some(&mut self.shield),
+        }
+    }
+
+    // Catching the same pickup again starts the effect over
+    pub fn start(&mut self, kind: PickupKind, duration: u32) {
+        if let Some(timer) = self.timer(kind) {
+            *timer = duration;
+        }
+    }
+
+    pub fn remaining(&self, kind: PickupKind) -> u32 {
+        match kind {
+            PickupKind::ExtraLife => 0,
+            PickupKind::Wildcard => self.wildcard,
+            PickupKind::SlowMotion => self.slow_motion,
+            PickupKind::Magnet => self.magnet,
+            PickupKind::Shield => self.shield,
+        }
+    }
+
+    pub fn is_active(&self, kind: PickupKind) -> bool {
+        self.remaining(kind) > 0
+    }
+
+    // The effects still going on, with their ticks left
+    pub fn active(&self) -> Vec<(PickupKind, u32)> {
+        PICKUPS
+            .iter()
+            .map(|&kind| (kind, self.remaining(kind)))
+            .filter(|&(_, remaining)| remaining > 0)
+            .collect()
+    }
+
+    // Called once per tick
+    pub fn update(&mut self) {
+        for &kind in &PICKUPS {
+            if let Some(timer) = self.timer(kind) {
+                *timer = timer.saturating_sub(1);
+            }
+        }
+    }
+}
@@ -1,13 +1,18 @@
 use actions::{Entity, MoveDirection, PlayerAction};
 use baddies::{Baddie, BaddieColor, BaddieFace};
+use config::{Config, GameOver};
 use constants::*;
 use ggez::{Context, GameResult};
-use graphics::{self, Point2, Rect, Vector2};
+use ggez::graphics::{self, Point2, Rect, Vector2};
+use pickups::{Effects, Pickup, PickupKind};
 use resources::Resources;
+use std::ops::Add;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct PlayerBody {
+    #[serde(with = "::serialization::point2")]
     position: Point2,
+    #[serde(with = "::serialization::vector2")]
     speed: Vector2,
     shielded: bool,
 }
@@ -21,18 +26,27 @@ impl PlayerBody {
         }
     }
 
-    pub fn radius(&self) -> f32 {
-        RADIUS - TOLERANCE + if self.shielded { 5.0 } else { 0.0 }
+    pub fn position(&self) -> Point2 {
+        self.position
     }
 
-    pub fn on_the_ground(&self) -> bool {
-        self.position.y >= MAX_Y
+    pub fn radius(&self, config: &Config) -> f32 {
+        config.radius - config.tolerance + if self.shielded { 5.0 } else { 0.0 }
+    }
+
+    pub fn on_the_ground(&self, config: &Config) -> bool {
+        self.position.y >= config.max_y()
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct PlayerController {
     index: u8,
     player: Option<Player>,
+    // false once the controller of the player is unplugged
+    connected: bool,
+    // what the player did during its previous lives, once dead
+    previous: Option<Stats>,
 }
 
 impl PlayerController {
@@ -40,22 +54,43 @@ impl PlayerController {
         PlayerController {
             index,
             player: None,
+            connected: true,
+            previous: None,
         }
     }
 
+    // HUD slot of the player
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn set_connected(&mut self, connected: bool) {
+        self.connected = connected;
+    }
+
     // proxy to player.draw
-    pub fn draw(&self, res: &Resources, ctx: &mut Context) -> GameResult<()> {
+    pub fn draw(
+        &self,
+        res: &Resources,
+        config: &Config,
+        alpha: f32,
+        ctx: &mut Context,
+    ) -> GameResult<()> {
         if let Some(ref player) = self.player {
-            player.draw(res, ctx)
+            player.draw(res, config, alpha, ctx)
         } else {
             Ok(())
         }
     }
 
     // proxy to player.overlaps
-    pub fn overlaps(&self, rect: &Rect) -> bool {
+    pub fn overlaps(&self, rect: &Rect, config: &Config) -> bool {
         if let Some(ref player) = self.player {
-            player.overlaps(rect)
+            player.overlaps(rect, config)
         } else {
             false
         }
@@ -63,10 +98,10 @@ impl PlayerController {
 
     // proxy to player.update
     // checks player's life before
-    pub fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+    pub fn update(&mut self, config: &Config) -> GameResult<()> {
         self.check_player_life();
         if let Some(ref mut player) = self.player {
-            player.update(ctx)
+            player.update(config)
         } else {
             Ok(())
         }
@@ -75,6 +110,8 @@ impl PlayerController {
     fn check_player_life(&mut self) {
         self.player = if let Some(player) = self.player.take() {
             if player.life == 0 {
+                let stats = player.stats();
+                self.previous = Some(self.previous.map_or(stats, |previous| previous + stats));
                 None
             } else {
                 Some(player)
@@ -84,9 +121,9 @@ impl PlayerController {
         };
     }
 
-    pub fn overlaps_player(&self, other: Option<PlayerBody>) -> bool {
+    pub fn overlaps_player(&self, other: Option<PlayerBody>, config: &Config) -> bool {
         if let (Some(player), Some(other)) = (self.player.as_ref(), other) {
-            player.overlaps_player(&other)
+            player.overlaps_player(&other, config)
         } else {
             false
         }
@@ -96,17 +133,77 @@ impl PlayerController {
         self.player.as_ref().map(|p| p.body())
     }
 
-    pub fn draw_ui(&self, res: &Resources, nb_players: usize, ctx: &mut Context) -> GameResult<()> {
+    pub fn player(&self) -> Option<&Player> {
+        self.player.as_ref()
+    }
+
+    pub fn score(&self) -> Option<u32> {
+        self.player.as_ref().map(|p| p.score)
+    }
+
+    pub fn life(&self) -> Option<i32> {
+        self.player.as_ref().map(|p| p.life)
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.player.is_none()
+    }
+
+    // Died at least once since the start of the game
+    pub fn has_died(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    // Stats of every life of the player
+    pub fn stats(&self) -> Option<Stats> {
+        match (self.player.as_ref().map(|p| p.stats()), self.previous) {
+            (Some(current), Some(previous)) => Some(previous + current),
+            (current, previous) => current.or(previous),
+        }
+    }
+
+    // Starts over with a new player, dead or alive
+    pub fn restart(&mut self, position: Point2, config: &Config) {
+        self.player = Some(Player::new(position, config));
+        self.previous = None;
+    }
+
+    // screen is the visible area, the HUD is laid out on its whole width
+    // nb_slots is the number of slots used, including the free ones before the last
+    pub fn draw_ui(
+        &self,
+        res: &Resources,
+        config: &Config,
+        screen: Rect,
+        nb_slots: usize,
+        ctx: &mut Context,
+    ) -> GameResult<()> {
         use self::graphics::*;
 
         let col = f32::from(self.index % 2);
         let line = f32::from(self.index / 2);
 
-        let max_width = WIDTH / nb_players.min(2) as f32;
-        let start_x = max_width * col;
-        let start_y = (LIFE_IMAGE_SIZE + UI_MARGIN) * line;
+        let max_width = screen.w / nb_slots.min(2) as f32;
+        let start_x = screen.x + max_width * col;
+        let start_y = screen.y + (LIFE_IMAGE_SIZE + UI_MARGIN) * line;
 
-        if let Some(ref player) = self.player {
+        if !self.connected {
+            set_color(ctx, Color::from_rgb(255, 255, 255))?;
+            let text = Text::new(
+                ctx,
+                &format!("PLAYER {}: CONTROLLER LOST", self.index + 1),
+                &res.font,
+            )?;
+            draw(
+                ctx,
+                &text,
+                Point2::new(
+                    start_x + UI_MARGIN * 2.0 + LIFE_IMAGE_SIZE,
+                    start_y + UI_MARGIN,
+                ),
+                0.0,
+            )?;
+        } else if let Some(ref player) = self.player {
             // draw thumb
             let radius = LIFE_IMAGE_SIZE / 2.0;
             let pos = Point2::new(start_x + UI_MARGIN + radius, start_y + UI_MARGIN + radius);
@@ -132,13 +229,20 @@ impl PlayerController {
                 circle(ctx, DrawMode::Fill, pos, radius, 0.1)?;
             }
 
-            // draw score
+            // draw score, and combo once there is one
             set_color(ctx, Color::from_rgb(255, 255, 255))?;
-            let text = Text::new(
-                ctx,
-                &format!("PLAYER {}: {}", self.index + 1, player.score),
-                &res.font,
-            )?;
+            let score = if player.combo > 1 {
+                format!(
+                    "PLAYER {}: {}  COMBO {} x{}",
+                    self.index + 1,
+                    player.score,
+                    player.combo,
+                    player.multiplier(config)
+                )
+            } else {
+                format!("PLAYER {}: {}", self.index + 1, player.score)
+            };
+            let text = Text::new(ctx, &score, &res.font)?;
             draw(
                 ctx,
                 &text,
@@ -156,14 +260,53 @@ impl PlayerController {
                 draw(ctx, &res.life, Point2::new(x, start_y + UI_MARGIN), 0.0)
                     .expect("Failed to draw a heart");
             });
-        } else {
-            // draw score
-            set_color(ctx, Color::from_rgb(255, 255, 255))?;
-            let text = Text::new(
+
+            // draw shield energy, greyed out while cooling down
+            let bar = Rect::new(
+                start_x + max_width - UI_MARGIN - SHIELD_BAR_WIDTH,
+                start_y + UI_MARGIN + LIFE_IMAGE_SIZE + 2.0,
+                SHIELD_BAR_WIDTH,
+                SHIELD_BAR_HEIGHT,
+            );
+            if player.shield_cooldown > 0 {
+                set_color(ctx, Color::from_rgb(100, 100, 100))?;
+            } else {
+                set_color(ctx, Color::from_rgb(255, 255, 255))?;
+            }
+            rectangle(ctx, DrawMode::Line(1.0), bar)?;
+            rectangle(
                 ctx,
-                &format!("PLAYER {}: RESPAWN", self.index + 1),
-                &res.font,
+                DrawMode::Fill,
+                Rect::new(bar.x, bar.y, bar.w * player.shield_energy, bar.h),
             )?;
+
+            // draw pickup effects, in seconds left, before the lifes
+            let effects = player.effects.active();
+            if !effects.is_empty() {
+                let effects: Vec<_> = effects
+                    .iter()
+                    .map(|&(kind, ticks)| {
+                        let seconds = (ticks + UPDATES_PER_SECOND - 1) / UPDATES_PER_SECOND;
+                        format!("{} {}", kind.name(), seconds)
+                    })
+                    .collect();
+                let text = Text::new(ctx, &effects.join("  "), &res.font)?;
+                let lifes = player.life.max(0) as f32;
+                let x = start_x + max_width - lifes * (LIFE_IMAGE_SIZE + UI_MARGIN) - UI_MARGIN
+                    - text.width() as f32;
+                set_color(ctx, Color::from_rgb(255, 255, 255))?;
+                draw(ctx, &text, Point2::new(x, start_y + UI_MARGIN), 0.0)?;
+            }
+        } else {
+            // draw score, dead players can't respawn until every player is dead
+            let message = match self.stats() {
+                Some(stats) if config.game_over == GameOver::AllDead => {
+                    format!("PLAYER {}: {} (OUT)", self.index + 1, stats.score)
+                }
+                _ => format!("PLAYER {}: RESPAWN", self.index + 1),
+            };
+            set_color(ctx, Color::from_rgb(255, 255, 255))?;
+            let text = Text::new(ctx, &message, &res.font)?;
             draw(
                 ctx,
                 &text,
@@ -178,52 +321,128 @@ impl PlayerController {
         Ok(())
     }
 
-    pub fn process_action(&mut self, action: PlayerAction) -> GameResult<()> {
+    pub fn process_action(&mut self, action: PlayerAction, config: &Config) -> GameResult<()> {
         match (action, &mut self.player) {
-            (PlayerAction::Spawn(pos), player @ &mut None) => *player = Some(Player::new(pos)),
+            (PlayerAction::Spawn(pos), player @ &mut None) => {
+                *player = Some(Player::new(pos, config))
+            }
             // Ignore spawn when the player is already in game
             // Ignore other actions when the player is not in game
             (PlayerAction::Spawn(_), &mut Some(_)) | (_, &mut None) => (),
-            (action, &mut Some(ref mut player)) => player.process_action(action)?,
+            (action, &mut Some(ref mut player)) => player.process_action(action, config)?,
         }
 
         Ok(())
     }
 }
 
-#[derive(Debug)]
+// What a player did during a life, shown on the results screen
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    pub score: u32,
+    pub captures: u32,
+    pub hits: u32,
+    pub best_combo: u32,
+    pub pickups: u32,
+    // ticks in game
+    pub survived: u32,
+}
+
+impl Add for Stats {
+    type Output = Stats;
+
+    fn add(self, other: Stats) -> Stats {
+        Stats {
+            score: self.score + other.score,
+            captures: self.captures + other.captures,
+            hits: self.hits + other.hits,
+            best_combo: self.best_combo.max(other.best_combo),
+            pickups: self.pickups + other.pickups,
+            survived: self.survived + other.survived,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Player {
     body: PlayerBody,
     captured: Option<(BaddieColor, BaddieFace)>,
     score: u32,
+    // matching captures in a row
+    combo: u32,
+    // ticks left before the combo loses a step
+    combo_timer: u32,
     life: i32,
     fast_attenuation: bool,
     current_direction: Option<MoveDirection>,
+    steering: f32,
+    // ticks left to jump as soon as possible
+    jump_buffer: u32,
+    // ticks left to jump, after leaving the ground
+    coyote: u32,
+    // rising after a jump, which can still be cut short
+    jumping: bool,
+    // jump released before the buffered jump happens
+    short_jump: bool,
+    // held shield button, the shield is only up when there is energy left
+    shield_held: bool,
+    // from 0 (empty) to 1 (full)
+    shield_energy: f32,
+    // ticks left before the shield can be used again, after being emptied
+    shield_cooldown: u32,
+    effects: Effects,
+    stats: Stats,
+    #[serde(with = "::serialization::point2")]
+    last_position: Point2,
 }
 
 impl Player {
-    pub fn new(position: Point2) -> Player {
+    pub fn new(position: Point2, config: &Config) -> Player {
         Player {
             body: PlayerBody::new(position),
             captured: None,
             score: 0,
-            life: START_PLAYER_LIFE,
+            combo: 0,
+            combo_timer: 0,
+            life: config.start_player_life,
             fast_attenuation: false,
             current_direction: None,
+            steering: 0.0,
+            jump_buffer: 0,
+            coyote: 0,
+            jumping: false,
+            short_jump: false,
+            shield_held: false,
+            shield_energy: 1.0,
+            shield_cooldown: 0,
+            effects: Effects::default(),
+            stats: Stats::default(),
+            last_position: position,
         }
     }
 
     /// Called upon each physics update to the game.
     /// This should be where the game's logic takes place.
-    pub fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
-        let wanted = self.wanted_speed();
+    pub fn update(&mut self, config: &Config) -> GameResult<()> {
+        self.last_position = self.body.position;
+        self.effects.update();
+        self.stats.survived += 1;
+
+        if self.jump_buffer > 0 {
+            self.jump_buffer -= 1;
+            if self.can_jump(config) {
+                self.jump(config);
+            }
+        }
+
+        let wanted = self.wanted_speed(config);
 
         let body = &mut self.body;
 
-        let damping = if body.on_the_ground() {
-            PLAYER_DAMPING
+        let damping = if body.on_the_ground(config) {
+            config.player_damping
         } else {
-            FLYING_DAMPING
+            config.flying_damping
         };
         if body.speed.x > wanted {
             body.speed.x = wanted.max(body.speed.x - damping);
@@ -232,27 +451,57 @@ impl Player {
         }
 
         body.position.x = (body.position.x + body.speed.x)
-            .min(WIDTH - RADIUS)
-            .max(RADIUS);
-        body.position.y = (body.position.y + body.speed.y).min(MAX_Y).max(0.0);
+            .min(WIDTH - config.radius)
+            .max(config.radius);
+        body.position.y = (body.position.y + body.speed.y)
+            .min(config.max_y())
+            .max(0.0);
 
-        if body.on_the_ground() {
+        if body.on_the_ground(config) {
             body.speed.y = 0.0;
+            self.jumping = false;
         } else {
-            body.speed.y += JUMP_ATTENUATION * if self.fast_attenuation {
-                FAST_ATTENUATION
+            body.speed.y += config.jump_attenuation * if self.fast_attenuation {
+                config.fast_attenuation
             } else {
                 1.0
             };
         }
 
+        self.coyote = if self.on_the_ground(config) {
+            config.coyote_time
+        } else {
+            self.coyote.saturating_sub(1)
+        };
+
+        self.update_shield(config);
+
+        if self.combo_timer > 0 {
+            self.combo_timer -= 1;
+            if self.combo_timer == 0 {
+                self.combo = self.combo.saturating_sub(config.combo_step);
+                if self.combo > 0 {
+                    self.combo_timer = config.combo_timeout;
+                }
+            }
+        }
+
         Ok(())
     }
 
-    pub fn draw(&self, res: &Resources, ctx: &mut Context) -> GameResult<()> {
+    // alpha is the progress between the previous update and the current one
+    pub fn draw(
+        &self,
+        res: &Resources,
+        config: &Config,
+        alpha: f32,
+        ctx: &mut Context,
+    ) -> GameResult<()> {
         use self::graphics::*;
 
         let body = &self.body;
+        let radius = config.radius;
+        let position = self.last_position + (body.position - self.last_position) * alpha;
 
         // draw player
         if let Some((color, face)) = self.captured {
@@ -261,66 +510,143 @@ impl Player {
             let img = &res.baddies_faces[&face];
             let Rect { w: iw, h: ih, .. } = img.get_dimensions();
 
-            let scale = Point2::new(RADIUS * 2.0 / iw, RADIUS * 2.0 / ih);
+            let scale = Point2::new(radius * 2.0 / iw, radius * 2.0 / ih);
 
             let params = DrawParam {
-                dest: self.rect().point(),
+                dest: Point2::new(position.x - radius, position.y - radius),
                 scale,
                 ..Default::default()
             };
 
-            circle(ctx, DrawMode::Fill, body.position, RADIUS, 0.1)?;
+            circle(ctx, DrawMode::Fill, position, radius, 0.1)?;
             draw_ex(ctx, img, params)?;
         } else {
             set_color(ctx, Color::from_rgb(255, 255, 255))?;
-            circle(ctx, DrawMode::Fill, body.position, RADIUS, 0.1)?;
+            circle(ctx, DrawMode::Fill, position, radius, 0.1)?;
         }
 
         if body.shielded {
-            circle(ctx, DrawMode::Line(1.0), body.position, RADIUS + 5.0, 0.1)?;
+            circle(ctx, DrawMode::Line(1.0), position, radius + 5.0, 0.1)?;
         }
 
-        Ok(())
-    }
+        // the shield of a pickup is thicker
+        if self.effects.is_active(PickupKind::Shield) {
+            set_color(ctx, PickupKind::Shield.into())?;
+            circle(ctx, DrawMode::Line(3.0), position, radius + 8.0, 0.1)?;
+        }
 
-    pub fn on_the_ground(&self) -> bool {
-        self.body.on_the_ground()
+        Ok(())
     }
 
-    pub fn rect(&self) -> Rect {
-        Rect::new(
-            self.body.position.x - RADIUS,
-            self.body.position.y - RADIUS,
-            RADIUS * 2.0,
-            RADIUS * 2.0,
-        )
+    pub fn on_the_ground(&self, config: &Config) -> bool {
+        self.body.on_the_ground(config)
     }
 
     pub fn body(&self) -> PlayerBody {
         self.body
     }
 
-    pub fn process_action(&mut self, action: PlayerAction) -> GameResult<()> {
-        let on_the_ground = self.on_the_ground();
+    pub fn captured(&self) -> Option<(BaddieColor, BaddieFace)> {
+        self.captured
+    }
+
+    pub fn effects(&self) -> &Effects {
+        &self.effects
+    }
+
+    pub fn stats(&self) -> Stats {
+        Stats {
+            score: self.score,
+            ..self.stats
+        }
+    }
+
+    fn update_shield(&mut self, config: &Config) {
+        if self.body.shielded {
+            self.shield_energy = (self.shield_energy - config.shield_drain).max(0.0);
+            if self.shield_energy == 0.0 {
+                self.body.shielded = false;
+                self.shield_cooldown = config.shield_cooldown;
+            }
+        } else {
+            self.shield_cooldown = self.shield_cooldown.saturating_sub(1);
+            self.shield_energy = (self.shield_energy + config.shield_recharge).min(1.0);
+
+            // a held button raises the shield again, once the cooldown is over
+            self.body.shielded = self.shield_held && self.can_shield();
+        }
+    }
+
+    // Points of the next capture
+    pub fn multiplier(&self, config: &Config) -> u32 {
+        (1 + self.combo / config.combo_step).min(config.combo_max_multiplier)
+    }
+
+    fn can_shield(&self) -> bool {
+        self.shield_cooldown == 0 && self.shield_energy > 0.0
+    }
+
+    fn can_jump(&self, config: &Config) -> bool {
+        self.on_the_ground(config) || self.coyote > 0
+    }
+
+    fn jump(&mut self, config: &Config) {
+        self.body.speed.y = -config.jump_height;
+        self.fast_attenuation = false;
+        self.jump_buffer = 0;
+        self.coyote = 0;
+        self.jumping = true;
+
+        // a buffered jump, already released, is cut from the start
+        if self.short_jump {
+            self.body.speed.y *= config.jump_cut;
+            self.jumping = false;
+            self.short_jump = false;
+        }
+    }
+
+    pub fn process_action(&mut self, action: PlayerAction, config: &Config) -> GameResult<()> {
+        let on_the_ground = self.on_the_ground(config);
 
         match action {
             PlayerAction::Move(dir) => self.current_direction = dir,
-            PlayerAction::Jump if on_the_ground => {
-                self.body.speed.y = -JUMP_HEIGHT;
-                self.fast_attenuation = false;
+            PlayerAction::Steer(steering) => self.steering = steering.max(-1.0).min(1.0),
+            PlayerAction::Jump(true) => {
+                self.short_jump = false;
+                if self.can_jump(config) {
+                    self.jump(config);
+                } else {
+                    // too early, the jump happens when landing
+                    self.jump_buffer = config.jump_buffer;
+                }
+            }
+            PlayerAction::Jump(false) if config.variable_jump => {
+                if self.jumping && self.body.speed.y < 0.0 {
+                    self.body.speed.y *= config.jump_cut;
+                }
+                self.jumping = false;
+                self.short_jump = self.jump_buffer > 0;
             }
             PlayerAction::Dump(dump) if !on_the_ground => self.fast_attenuation = dump,
-            PlayerAction::Shield(shield) => self.body.shielded = shield,
-            PlayerAction::Collides(Entity::Baddie(baddie)) => self.collides_with_baddie(&baddie),
-            PlayerAction::Collides(Entity::Player(other)) => self.collides_with_player(&other),
+            PlayerAction::Shield(shield) => {
+                self.shield_held = shield;
+                self.body.shielded = shield && self.can_shield();
+            }
+            PlayerAction::Collides(Entity::Baddie(baddie)) => {
+                self.collides_with_baddie(&baddie, config)
+            }
+            PlayerAction::Collides(Entity::Player(other)) => {
+                self.collides_with_player(&other, config)
+            }
+            PlayerAction::Collides(Entity::Pickup(pickup)) => self.picks_up(&pickup, config),
             _ => (),
         }
 
         Ok(())
     }
 
-    pub fn collides_with_baddie(&mut self, baddie: &Baddie) {
-        if self.body.shielded {
+    pub fn collides_with_baddie(&mut self, baddie: &Baddie, config: &Config) {
+        if self.body.shielded || self.effects.is_active(PickupKind::Shield) {
             return;
         }
 
@@ -328,14 +654,23 @@ impl Player {
         let pos = Point2::new(x + w / 2.0, y + h / 2.0);
 
         self.captured = if let Some((c, f)) = self.captured.take() {
-            if c == baddie.color || f == baddie.face {
-                self.score += 1;
+            if c == baddie.color || f == baddie.face
+                || self.effects.is_active(PickupKind::Wildcard)
+            {
+                self.score += self.multiplier(config);
+                self.combo += 1;
+                self.combo_timer = config.combo_timeout;
+                self.stats.captures += 1;
+                self.stats.best_combo = self.stats.best_combo.max(self.combo);
                 Some((baddie.color, baddie.face))
             } else {
                 self.score = self.score.saturating_sub(1);
+                self.combo = 0;
+                self.combo_timer = 0;
+                self.stats.hits += 1;
 
                 let mut dir = self.body.position - pos;
-                if self.on_the_ground() {
+                if self.on_the_ground(config) {
                     dir.y = 0.0;
                 }
                 let dir = dir.normalize() * (w / 5.0);
@@ -345,16 +680,25 @@ impl Player {
                 None
             }
         } else {
+            self.stats.captures += 1;
             Some((baddie.color, baddie.face))
         };
     }
 
-    pub fn collides_with_player(&mut self, other: &PlayerBody) {
+    pub fn picks_up(&mut self, pickup: &Pickup, config: &Config) {
+        self.stats.pickups += 1;
+        match pickup.kind {
+            PickupKind::ExtraLife => self.life += 1,
+            kind => self.effects.start(kind, config.pickups.rule(kind).duration),
+        }
+    }
+
+    pub fn collides_with_player(&mut self, other: &PlayerBody, config: &Config) {
         // swap speed
         self.body.speed = other.speed;
 
-        let my_radius = self.body.radius();
-        let their_radius = other.radius();
+        let my_radius = self.body.radius(config);
+        let their_radius = other.radius(config);
 
         let diff = self.body.position - other.position;
 
@@ -366,19 +710,19 @@ impl Player {
         self.body.speed.y += diff.y * factor;
     }
 
-    pub fn overlaps(&self, rect: &Rect) -> bool {
+    pub fn overlaps(&self, rect: &Rect, config: &Config) -> bool {
         let body = &self.body;
-        let radius = body.radius();
+        let radius = body.radius(config);
 
         let dx = body.position.x - rect.x.max(body.position.x.min(rect.x + rect.w));
         let dy = body.position.y - rect.y.max(body.position.y.min(rect.y + rect.h));
         (dx * dx + dy * dy) < (radius * radius)
     }
 
-    pub fn overlaps_player(&self, other: &PlayerBody) -> bool {
+    pub fn overlaps_player(&self, other: &PlayerBody, config: &Config) -> bool {
         let body = &self.body;
-        let my_radius = body.radius();
-        let their_radius = other.radius();
+        let my_radius = body.radius(config);
+        let their_radius = other.radius(config);
 
         let diff = body.position - other.position;
 
@@ -386,13 +730,144 @@ impl Player {
         (my_radius + their_radius) * (my_radius + their_radius) > square_dist
     }
 
-    fn wanted_speed(&self) -> f32 {
+    fn wanted_speed(&self, config: &Config) -> f32 {
         use self::MoveDirection::*;
 
+        // digital directions win over the analog stick
         match self.current_direction {
-            None => 0.0,
-            Some(Left) => -PLAYER_SPEED,
-            Some(Right) => PLAYER_SPEED,
+            None => self.steering * config.player_speed,
+            Some(Left) => -config.player_speed,
+            Some(Right) => config.player_speed,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A player falling from height pixels above the ground, without any speed
+    fn falling(height: f32, config: &Config) -> Player {
+        Player::new(Point2::new(WIDTH / 2.0, config.max_y() - height), config)
+    }
+
+    // Updates before the player lands
+    fn updates_to_land(height: f32, config: &Config) -> u32 {
+        let mut player = falling(height, config);
+        let mut updates = 0;
+        while !player.on_the_ground(config) {
+            player.update(config).unwrap();
+            updates += 1;
+        }
+        updates
+    }
+
+    fn jumped(player: &Player) -> bool {
+        player.body.speed.y < 0.0
+    }
+
+    // Presses jump `early` updates before landing, returns whether the player jumps
+    fn jump_before_landing(early: u32, config: &Config) -> bool {
+        let height = 200.0;
+        let mut player = falling(height, config);
+        for _ in early..updates_to_land(height, config) {
+            player.update(config).unwrap();
+        }
+
+        player.process_action(PlayerAction::Jump(true), config).unwrap();
+        assert!(!jumped(&player));
+
+        for _ in 0..early {
+            player.update(config).unwrap();
+        }
+        assert!(player.on_the_ground(config));
+
+        player.update(config).unwrap();
+        jumped(&player)
+    }
+
+    #[test]
+    fn buffered_jump_happens_on_landing() {
+        let config = Config::default();
+        assert!(jump_before_landing(config.jump_buffer - 1, &config));
+    }
+
+    #[test]
+    fn buffered_tap_is_a_short_jump() {
+        let config = Config::default();
+        let height = 200.0;
+        let mut player = falling(height, &config);
+        for _ in 2..updates_to_land(height, &config) {
+            player.update(&config).unwrap();
+        }
+
+        player.process_action(PlayerAction::Jump(true), &config).unwrap();
+        player.process_action(PlayerAction::Jump(false), &config).unwrap();
+        for _ in 0..3 {
+            player.update(&config).unwrap();
+        }
+
+        // a full jump would be faster
+        assert!(jumped(&player));
+        assert!(player.body.speed.y > -config.jump_height * config.jump_cut);
+    }
+
+    #[test]
+    fn early_jump_is_forgotten() {
+        let config = Config::default();
+        assert!(!jump_before_landing(config.jump_buffer, &config));
+        assert!(!jump_before_landing(config.jump_buffer + 3, &config));
+    }
+
+    // Leaves the ground, then presses jump `late` updates after
+    fn jump_after_leaving_ground(late: u32, config: &Config) -> bool {
+        let mut player = falling(0.0, config);
+        player.update(config).unwrap();
+        assert!(player.on_the_ground(config));
+
+        player.body.position.y -= 100.0;
+        for _ in 0..late {
+            player.update(config).unwrap();
+        }
+        assert!(!player.on_the_ground(config));
+
+        player.process_action(PlayerAction::Jump(true), config).unwrap();
+        player.body.speed.y == -config.jump_height
+    }
+
+    #[test]
+    fn coyote_jump_after_leaving_the_ground() {
+        let config = Config::default();
+        assert!(jump_after_leaving_ground(0, &config));
+        assert!(jump_after_leaving_ground(config.coyote_time - 1, &config));
+    }
+
+    #[test]
+    fn no_jump_once_coyote_time_is_over() {
+        let config = Config::default();
+        assert!(!jump_after_leaving_ground(config.coyote_time, &config));
+        assert!(!jump_after_leaving_ground(config.coyote_time + 3, &config));
+    }
+
+    #[test]
+    fn held_shield_is_raised_again_after_the_cooldown() {
+        let config = Config::default();
+        let mut player = falling(0.0, &config);
+        player.process_action(PlayerAction::Shield(true), &config).unwrap();
+        assert!(player.body.shielded);
+
+        while player.body.shielded {
+            player.update(&config).unwrap();
+        }
+        for _ in 0..config.shield_cooldown {
+            assert!(!player.body.shielded);
+            player.update(&config).unwrap();
+        }
+        assert!(player.body.shielded);
+
+        // released, it stays down
+        player.process_action(PlayerAction::Shield(false), &config).unwrap();
+        player.update(&config).unwrap();
+        assert!(!player.body.shielded);
+    }
+}
@@ -0,0 +1,262 @@
+use actions::{Action, GameAction};
+use config::Config;
+use constants::*;
+use ggez::{GameError, GameResult};
+use serde_json;
+use simulation::{PlayerId, Simulation};
+use waves::{self, Wave};
+use std::io::{Read, Write};
+
+// Bumped each time older replay files can't be read anymore.
+// Changes of the simulation rules are caught by comparing the final scores instead.
+pub const REPLAY_VERSION: u32 = 13;
+
+// Everything needed to play a session again:
+// the seed, the game configuration and every input action with its tick.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    version: u32,
+    seed: u64,
+    updates_per_second: u32,
+    config: Config,
+    // empty for the endless waves
+    waves: Vec<Wave>,
+    actions: Vec<(u64, Action)>,
+    // tick at which the session ended, the last action if unknown
+    #[serde(default)]
+    end: Option<u64>,
+    // score of each player when the session ended
+    #[serde(default)]
+    scores: Option<Vec<(PlayerId, u32)>>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, config: Config, waves: Vec<Wave>) -> Replay {
+        Replay {
+            version: REPLAY_VERSION,
+            seed,
+            updates_per_second: UPDATES_PER_SECOND,
+            config,
+            waves,
+            actions: Vec::new(),
+            end: None,
+            scores: None,
+        }
+    }
+
+    // tick is the simulation tick which will process the action
+    pub fn record(&mut self, tick: u64, action: Action) {
+        self.actions.push((tick, action));
+    }
+
+    // The session ends with this state, even without any action left
+    pub fn finish(&mut self, simulation: &Simulation) {
+        self.end = Some(simulation.tick());
+        self.scores = Some(scores(simulation));
+    }
+
+    pub fn load<R: Read>(reader: R) -> GameResult<Replay> {
+        let replay: Replay = serde_json::from_reader(reader)
+            .map_err(|e| GameError::ResourceLoadError(format!("Invalid replay: {}", e)))?;
+
+        if replay.version != REPLAY_VERSION {
+            return Err(GameError::ResourceLoadError(format!(
+                "Unsupported replay version {} (expected {})",
+                replay.version, REPLAY_VERSION
+            )));
+        }
+
+        if replay.updates_per_second != UPDATES_PER_SECOND {
+            return Err(GameError::ResourceLoadError(format!(
+                "Replay recorded at {} updates per second (expected {})",
+                replay.updates_per_second, UPDATES_PER_SECOND
+            )));
+        }
+
+        // a broken configuration would only fail later, in the middle of the game
+        replay.config.validate()?;
+        waves::validate(&replay.waves, "replay")?;
+
+        Ok(replay)
+    }
+
+    pub fn save<W: Write>(&self, writer: W) -> GameResult<()> {
+        serde_json::to_writer(writer, self)
+            .map_err(|e| GameError::UnknownError(format!("Failed to write replay: {}", e)))
+    }
+}
+
+// Score of every player, dead or alive
+fn scores(simulation: &Simulation) -> Vec<(PlayerId, u32)> {
+    simulation
+        .players()
+        .iter()
+        .filter_map(|(&id, player)| player.stats().map(|stats| (id, stats.score)))
+        .collect()
+}
+
+// Feeds the recorded actions to a simulation, tick by tick
+pub struct Playback {
+    replay: Replay,
+    next_action: usize,
+    // ticks played so far
+    tick: u64,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        Playback {
+            replay,
+            next_action: 0,
+            tick: 0,
+        }
+    }
+
+    // A fresh simulation, ready to play the replay
+    pub fn simulation(&self) -> GameResult<Simulation> {
+        Simulation::with_waves(
+            self.replay.seed,
+            self.replay.config.clone(),
+            self.replay.waves.clone(),
+        )
+    }
+
+    pub fn is_over(&self) -> bool {
+        match self.replay.end {
+            Some(end) => self.tick >= end,
+            None => self.next_action >= self.replay.actions.len(),
+        }
+    }
+
+    // Queues the actions recorded for the current tick, then updates the simulation
+    pub fn step(&mut self, simulation: &mut Simulation) -> GameResult<()> {
+        while let Some(&(tick, action)) = self.replay.actions.get(self.next_action) {
+            if tick > simulation.tick() {
+                break;
+            }
+
+            match action {
+                // The recorded session ends here, but the playback should not
+                Action::Game(GameAction::Quit) => (),
+                action => simulation.add_action(action),
+            }
+            self.next_action += 1;
+        }
+
+        self.tick += 1;
+        simulation.update()
+    }
+
+    // Fails when the replay ends with other scores than the recorded session,
+    // the simulation rules changed since
+    pub fn check(&self, simulation: &Simulation) -> GameResult<()> {
+        match self.replay.scores {
+            Some(ref expected) if *expected != scores(simulation) => {
+                Err(GameError::UnknownError(format!(
+                    "The replay diverged from the recorded session: expected scores {:?}, got {:?}",
+                    expected,
+                    scores(simulation)
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Plays the whole replay without rendering anything, returning the final state
+    pub fn run(&mut self) -> GameResult<Simulation> {
+        self.next_action = 0;
+        self.tick = 0;
+
+        let mut simulation = self.simulation()?;
+        while !self.is_over() {
+            self.step(&mut simulation)?;
+        }
+        Ok(simulation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actions::{MoveDirection, PlayerAction};
+
+    // A short session of two players, ended by closing the window
+    fn recorded() -> Replay {
+        let (arrows, zqsd) = (PlayerId::Keyboard(0), PlayerId::Keyboard(1));
+        let mut replay = Replay::new(1234, Config::default(), Vec::new());
+
+        replay.record(0, GameAction::Spawn(arrows).into());
+        replay.record(0, GameAction::Spawn(zqsd).into());
+        for i in 0..20 {
+            let dir = if i % 2 == 0 {
+                MoveDirection::Left
+            } else {
+                MoveDirection::Right
+            };
+            replay.record(i * 60, (PlayerAction::Move(Some(dir)), arrows).into());
+            replay.record(i * 60 + 20, (PlayerAction::Jump(true), zqsd).into());
+            replay.record(i * 60 + 30, (PlayerAction::Jump(false), zqsd).into());
+        }
+        replay.end = Some(1800);
+        replay
+    }
+
+    #[test]
+    fn replay_runs_identically() {
+        let replay = recorded();
+
+        let mut file = Vec::new();
+        replay.save(&mut file).unwrap();
+        let loaded = Replay::load(&file[..]).unwrap();
+
+        let first = Playback::new(replay).run().unwrap();
+        let second = Playback::new(loaded).run().unwrap();
+
+        assert_eq!(first.tick(), second.tick());
+        assert_eq!(scores(&first), scores(&second));
+        assert_eq!(first.baddies(), second.baddies());
+    }
+
+    #[test]
+    fn replay_plays_until_the_end_of_the_session() {
+        let simulation = Playback::new(recorded()).run().unwrap();
+        assert_eq!(simulation.tick(), 1800);
+    }
+
+    #[test]
+    fn invalid_config_is_refused() {
+        let mut replay = recorded();
+        replay.config.levels[0].spawn_frequency = 0;
+
+        let mut file = Vec::new();
+        replay.save(&mut file).unwrap();
+        assert!(Replay::load(&file[..]).is_err());
+    }
+
+    #[test]
+    fn rule_changes_are_detected() {
+        let mut replay = recorded();
+        let simulation = Playback::new(replay.clone()).run().unwrap();
+        replay.finish(&simulation);
+
+        let mut playback = Playback::new(replay.clone());
+        let again = playback.run().unwrap();
+        assert!(playback.check(&again).is_ok());
+
+        // as if a rule changed the outcome of the session
+        replay.scores = Some(vec![(PlayerId::Keyboard(0), 1000)]);
+        let playback = Playback::new(replay);
+        assert!(playback.check(&again).is_err());
+    }
+
+    // A session recorded with the current rules, to record again when they change on purpose
+    #[test]
+    fn recorded_session_plays_the_same() {
+        let file = include_str!("../tests/replays/two_players.json");
+        let mut playback = Playback::new(Replay::load(file.as_bytes()).unwrap());
+
+        let simulation = playback.run().unwrap();
+        assert!(playback.replay.scores.is_some());
+        playback.check(&simulation).unwrap();
+    }
+}
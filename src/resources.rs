@@ -1,4 +1,5 @@
 use baddies::BaddieFace;
+use pickups::{PickupKind, PICKUPS};
 use ggez::{Context, GameResult};
 use ggez::graphics::{Font, Image, Text};
 use std::collections::HashMap;
@@ -8,9 +9,13 @@ pub struct Resources {
     pub baddies_bg: Image,
     pub baddies_faces: HashMap<BaddieFace, Image>,
     pub life: Image,
+    // symbols of the pickups, but the extra life
+    pub pickups: HashMap<PickupKind, Text>,
     pub font: Font,
     pub pause: Text,
     pub waiting: Text,
+    pub game_over: Text,
+    pub restart: Text,
 }
 
 impl Resources {
@@ -25,12 +30,20 @@ impl Resources {
 
         let font = Font::new(ctx, "/DejaVuSerif.ttf", 25)?;
 
+        let mut pickups = HashMap::new();
+        for &kind in PICKUPS.iter().filter(|&&kind| kind != PickupKind::ExtraLife) {
+            pickups.insert(kind, Text::new(ctx, kind.symbol(), &font)?);
+        }
+
         Ok(Resources {
             baddies_bg: Image::new(ctx, "/white.png")?,
             baddies_faces,
             life: Image::new(ctx, "/life.png")?,
+            pickups,
             pause: Text::new(ctx, "PAUSED", &font)?,
             waiting: Text::new(ctx, "WAITING FOR A PLAYER", &font)?,
+            game_over: Text::new(ctx, "GAME OVER", &font)?,
+            restart: Text::new(ctx, "PRESS PAUSE TO PLAY AGAIN", &font)?,
             font,
         })
     }
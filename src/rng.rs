@@ -0,0 +1,37 @@
+use rand::Rng;
+use std::num::Wrapping;
+
+// The xorshift algorithm of rand's XorShiftRng,
+// whose state can be saved along with the game.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameRng {
+    x: u32,
+    y: u32,
+    z: u32,
+    w: u32,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> GameRng {
+        // xorshift can't be seeded with zeros only, hence the constant half
+        GameRng {
+            x: seed as u32,
+            y: (seed >> 32) as u32,
+            z: 0x9e37_79b9,
+            w: 0x7f4a_7c15,
+        }
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        let x = Wrapping(self.x);
+        let t = x ^ (x << 11);
+        self.x = self.y;
+        self.y = self.z;
+        self.z = self.w;
+        let w = Wrapping(self.w);
+        self.w = (w ^ (w >> 19) ^ (t ^ (t >> 8))).0;
+        self.w
+    }
+}
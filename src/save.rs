@@ -0,0 +1,107 @@
+use ggez::{GameError, GameResult};
+use serde_json;
+use simulation::Simulation;
+use waves;
+use std::io::{Read, Write};
+
+// Bumped each time the save format or the simulation state change
+pub const SAVE_VERSION: u32 = 17;
+
+pub const QUICKSAVE_PATH: &str = "/quicksave.json";
+
+#[derive(Serialize)]
+struct SaveGameRef<'a> {
+    version: u32,
+    simulation: &'a Simulation,
+}
+
+#[derive(Deserialize)]
+struct SaveGame {
+    version: u32,
+    simulation: Simulation,
+}
+
+pub fn save<W: Write>(simulation: &Simulation, writer: W) -> GameResult<()> {
+    let save = SaveGameRef {
+        version: SAVE_VERSION,
+        simulation,
+    };
+
+    serde_json::to_writer(writer, &save)
+        .map_err(|e| GameError::UnknownError(format!("Failed to write save: {}", e)))
+}
+
+pub fn load<R: Read>(reader: R) -> GameResult<Simulation> {
+    let save: SaveGame = serde_json::from_reader(reader)
+        .map_err(|e| GameError::ResourceLoadError(format!("Invalid save: {}", e)))?;
+
+    if save.version != SAVE_VERSION {
+        return Err(GameError::ResourceLoadError(format!(
+            "Unsupported save version {} (expected {})",
+            save.version, SAVE_VERSION
+        )));
+    }
+
+    // a broken configuration would only fail later, in the middle of the game
+    save.simulation.config().validate()?;
+    waves::validate(save.simulation.waves().waves(), "save")?;
+
+    Ok(save.simulation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actions::{GameAction, MoveDirection, PlayerAction};
+    use config::Config;
+    use simulation::PlayerId;
+
+    const PLAYER: PlayerId = PlayerId::Keyboard(0);
+
+    // The same inputs, every few ticks
+    fn play(simulation: &mut Simulation, ticks: u64) {
+        for _ in 0..ticks {
+            let action = match simulation.tick() % 90 {
+                0 => Some(PlayerAction::Move(Some(MoveDirection::Left))),
+                30 => Some(PlayerAction::Jump(true)),
+                40 => Some(PlayerAction::Jump(false)),
+                45 => Some(PlayerAction::Move(Some(MoveDirection::Right))),
+                _ => None,
+            };
+            if let Some(action) = action {
+                simulation.add_action((action, PLAYER));
+            }
+            simulation.update().unwrap();
+        }
+    }
+
+    #[test]
+    fn loaded_game_continues_identically() {
+        let mut simulation = Simulation::new(42, Config::default()).unwrap();
+        simulation.add_action(GameAction::Spawn(PLAYER));
+        play(&mut simulation, 600);
+
+        let mut file = Vec::new();
+        save(&simulation, &mut file).unwrap();
+        let mut loaded = load(&file[..]).unwrap();
+
+        play(&mut simulation, 1200);
+        play(&mut loaded, 1200);
+
+        assert_eq!(simulation.tick(), loaded.tick());
+        assert_eq!(
+            simulation.players().keys().collect::<Vec<_>>(),
+            loaded.players().keys().collect::<Vec<_>>()
+        );
+        for (id, player) in simulation.players() {
+            let other = &loaded.players()[id];
+            assert_eq!(player.score(), other.score());
+            assert_eq!(player.life(), other.life());
+            assert_eq!(
+                player.body().map(|body| body.position()),
+                other.body().map(|body| body.position())
+            );
+        }
+        assert_eq!(simulation.baddies(), loaded.baddies());
+    }
+}
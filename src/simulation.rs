@@ -0,0 +1,367 @@
+use actions::{Action, GameAction, PlayerAction};
+use baddies::Baddie;
+use config::{Config, Difficulty, GameOver};
+use constants::*;
+use ggez::GameResult;
+use ggez::graphics::Point2;
+use pickups::{Pickup, PickupKind};
+use player::PlayerController;
+use rng::GameRng;
+use waves::{self, Wave, WaveScheduler};
+use std::collections::BTreeMap;
+use std::iter;
+
+pub type ControllerId = i32;
+// Position of the keyboard scheme in the bindings
+pub type KeyboardId = u8;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum PlayerId {
+    Keyboard(KeyboardId),
+    Controller(ControllerId),
+}
+
+impl From<ControllerId> for PlayerId {
+    fn from(id: ControllerId) -> Self {
+        PlayerId::Controller(id)
+    }
+}
+
+// The whole game logic, without any rendering or input handling.
+// Each call to `update` advances the game by exactly one tick,
+// using the actions queued since the previous one.
+// Given the same seed and the same actions, two simulations stay identical.
+#[derive(Serialize, Deserialize)]
+pub struct Simulation {
+    config: Config,
+    seed: u64,
+    rng: GameRng,
+    #[serde(with = "::serialization::map_as_pairs")]
+    players: BTreeMap<PlayerId, PlayerController>,
+    actions: Vec<Action>,
+    baddies: Vec<Baddie>,
+    pickups: Vec<Pickup>,
+    waves: WaveScheduler,
+    paused: bool,
+    // every player is dead, or the first one in versus
+    over: bool,
+    #[serde(skip)]
+    quit: bool,
+    timer: u32,
+    tick: u64,
+    // score of the players who left this game, still counted in the level
+    left_score: u32,
+}
+
+impl Simulation {
+    // Fails when the configuration is invalid
+    pub fn new(seed: u64, config: Config) -> GameResult<Simulation> {
+        Simulation::with_waves(seed, config, Vec::new())
+    }
+
+    // Baddies come from the waves, or endlessly when there is none.
+    // Fails when the configuration or the waves are invalid.
+    pub fn with_waves(seed: u64, config: Config, waves: Vec<Wave>) -> GameResult<Simulation> {
+        config.validate()?;
+        waves::validate(&waves, "simulation")?;
+
+        Ok(Simulation {
+            config,
+            seed,
+            rng: GameRng::new(seed),
+            players: BTreeMap::new(),
+            actions: Vec::new(),
+            baddies: Vec::new(),
+            pickups: Vec::new(),
+            waves: WaveScheduler::new(waves),
+            paused: false,
+            over: false,
+            quit: false,
+            timer: 0,
+            tick: 0,
+            left_score: 0,
+        })
+    }
+
+    pub fn add_action<A: Into<Action>>(&mut self, action: A) {
+        self.actions.push(action.into());
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // Applies new tuning values, keeping the players and baddies as they are,
+    // an invalid configuration is refused
+    pub fn set_config(&mut self, config: Config) -> GameResult<()> {
+        config.validate()?;
+        for change in self.config.changes(&config) {
+            info!("Config changed, {}", change);
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Number of updates since the beginning, including paused ones
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn players(&self) -> &BTreeMap<PlayerId, PlayerController> {
+        &self.players
+    }
+
+    pub fn has_player(&self, id: PlayerId) -> bool {
+        self.players.contains_key(&id)
+    }
+
+    pub fn baddies(&self) -> &[Baddie] {
+        &self.baddies
+    }
+
+    pub fn pickups(&self) -> &[Pickup] {
+        &self.pickups
+    }
+
+    pub fn waves(&self) -> &WaveScheduler {
+        &self.waves
+    }
+
+    // Index of the current level in the configuration
+    pub fn level(&self) -> usize {
+        let progress = match self.config.difficulty {
+            Difficulty::Time => self.timer / UPDATES_PER_SECOND,
+            // dead players and those who left too, so that the level never drops
+            Difficulty::Score => self.players
+                .values()
+                .filter_map(|p| p.stats())
+                .map(|stats| stats.score)
+                .sum::<u32>() + self.left_score,
+        };
+
+        self.config
+            .levels
+            .iter()
+            .rposition(|level| level.start <= progress)
+            .unwrap_or(0)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    pub fn quit_requested(&self) -> bool {
+        self.quit
+    }
+
+    fn process_actions(&mut self) -> GameResult<()> {
+        use self::GameAction::*;
+
+        for action in &self.actions {
+            match *action {
+                Action::Game(Pause) => self.paused = !self.paused,
+                Action::Game(Suspend) => self.paused = true,
+                Action::Game(Quit) => self.quit = true,
+                Action::Game(Restart) => {
+                    self.baddies.clear();
+                    self.pickups.clear();
+                    self.waves.restart();
+                    self.timer = 0;
+                    self.left_score = 0;
+                    self.paused = false;
+                    self.over = false;
+
+                    let position = Point2::new(WIDTH / 2.0, self.config.max_y());
+                    for controller in self.players.values_mut() {
+                        controller.restart(position, &self.config);
+                    }
+                }
+                // everyone joins the next game
+                Action::Game(Spawn(_)) if self.over => (),
+                // dead players wait for the others, until the next game
+                Action::Game(Spawn(id))
+                    if self.config.game_over == GameOver::AllDead
+                        && self.players.get(&id).map_or(false, |p| p.has_died()) => (),
+                Action::Game(Spawn(id)) => {
+                    // the first free slot, left by another player or never used
+                    let index = (0..)
+                        .find(|&index| self.players.values().all(|p| p.index() != index))
+                        .unwrap();
+                    let controller = self.players
+                        .entry(id)
+                        .or_insert_with(|| PlayerController::new(index));
+                    let position = Point2::new(WIDTH / 2.0, self.config.max_y());
+                    controller.process_action(PlayerAction::Spawn(position), &self.config)?;
+                }
+                Action::Game(Leave(id)) => {
+                    if let Some(stats) = self.players.remove(&id).and_then(|p| p.stats()) {
+                        self.left_score += stats.score;
+                    }
+                }
+                Action::Game(Disconnect(id)) => {
+                    if let Some(controller) = self.players.get_mut(&id) {
+                        controller.set_connected(false);
+                        self.paused = true;
+                    }
+                }
+                Action::Game(Reconnect(from, to)) => {
+                    if !self.players.contains_key(&to) {
+                        if let Some(mut controller) = self.players.remove(&from) {
+                            controller.set_connected(true);
+                            self.players.insert(to, controller);
+                        }
+                    }
+                }
+                // held inputs are still followed, so that none is stuck when the game resumes
+                Action::Player(PlayerAction::Jump(true), _) if self.paused => (),
+                // the player may have left since
+                Action::Player(a, id) => if let Some(controller) = self.players.get_mut(&id) {
+                    controller.process_action(a, &self.config)?
+                },
+            }
+        }
+
+        self.actions.clear();
+        Ok(())
+    }
+
+    /// Advances the game by one tick.
+    pub fn update(&mut self) -> GameResult<()> {
+        self.process_actions()?;
+        self.tick += 1;
+
+        if self.players.is_empty() || self.paused || self.over {
+            return Ok(());
+        }
+
+        // Update players
+        for p in self.players.values_mut() {
+            p.update(&self.config)?;
+        }
+
+        let dead = self.players.values().filter(|p| p.is_dead()).count();
+        self.over = match self.config.game_over {
+            GameOver::AllDead => dead == self.players.len(),
+            GameOver::FirstDead => dead > 0,
+        };
+        if self.over {
+            info!("Game over after {} ticks", self.timer);
+            return Ok(());
+        }
+
+        let overlapping_players = {
+            let players_id = self.players.keys().cloned();
+            players_id
+                .clone()
+                .enumerate()
+                .flat_map(|(i, p1)| iter::repeat(p1).zip(players_id.clone().skip(i + 1)))
+                .filter(|&(p1, p2)| {
+                    self.players[&p1].overlaps_player(self.players[&p2].body(), &self.config)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for &(p1, p2) in &overlapping_players {
+            let player1 = self.players[&p1].body().unwrap();
+            let player2 = self.players[&p2].body().unwrap();
+            self.add_action((PlayerAction::Collides(player2.into()), p1));
+            self.add_action((PlayerAction::Collides(player1.into()), p2));
+        }
+
+        // Update baddies
+        let level = &self.config.levels[self.level()];
+        if let Some(baddie) = self.waves.update(&mut self.rng, level, self.timer) {
+            self.baddies.push(baddie);
+        }
+
+        self.baddies.retain(|b| b.body.y < HEIGHT);
+
+        // used in place of drain_filter...
+        let mut i = 0;
+        while i != self.baddies.len() {
+            if let Some((&id, _)) = self.players
+                .iter()
+                .find(|&(_, p)| p.overlaps(&self.baddies[i].body, &self.config))
+            {
+                let baddie = self.baddies.remove(i);
+                self.add_action((PlayerAction::Collides(baddie.into()), id));
+                break;
+            } else {
+                i += 1;
+            }
+        }
+
+        let players: Vec<_> = self.players.values().filter_map(|p| p.player()).collect();
+        let targets: Vec<_> = players.iter().map(|p| p.body().position()).collect();
+
+        // a single slow-motion slows the baddies down for everyone
+        let time_scale = if players
+            .iter()
+            .any(|p| p.effects().is_active(PickupKind::SlowMotion))
+        {
+            self.config.slow_motion_factor
+        } else {
+            1.0
+        };
+
+        // magnets only draw the baddies their player can capture
+        let magnets: Vec<_> = players
+            .iter()
+            .filter(|p| p.effects().is_active(PickupKind::Magnet))
+            .map(|p| {
+                let captured = if p.effects().is_active(PickupKind::Wildcard) {
+                    None
+                } else {
+                    p.captured()
+                };
+                (p.body().position(), captured)
+            })
+            .collect();
+
+        for baddie in &mut self.baddies {
+            baddie.update(&targets, time_scale)?;
+
+            for &(position, captured) in &magnets {
+                match captured {
+                    Some((color, face)) if color != baddie.color && face != baddie.face => (),
+                    _ => baddie.pull(position, self.config.magnet_strength * time_scale),
+                }
+            }
+        }
+
+        // Update pickups
+        let pickups = Pickup::spawn(&mut self.rng, &self.config);
+        self.pickups.extend(pickups);
+
+        self.pickups.retain(|p| p.body.y < HEIGHT);
+
+        let mut i = 0;
+        while i != self.pickups.len() {
+            if let Some((&id, _)) = self.players
+                .iter()
+                .find(|&(_, p)| p.overlaps(&self.pickups[i].body, &self.config))
+            {
+                let pickup = self.pickups.remove(i);
+                self.add_action((PlayerAction::Collides(pickup.into()), id));
+            } else {
+                i += 1;
+            }
+        }
+
+        for pickup in &mut self.pickups {
+            pickup.update(&self.config);
+        }
+
+        self.timer += 1;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::{Duration, Instant};
+
+// Time between two checks of the files or devices watched, in milliseconds
+const POLL_INTERVAL: u64 = 500;
+
+// Limits a check to once every POLL_INTERVAL, the first check is never delayed
+pub struct Throttle {
+    interval: Duration,
+    last_check: Option<Instant>,
+}
+
+impl Throttle {
+    pub fn new() -> Throttle {
+        Throttle {
+            interval: Duration::from_millis(POLL_INTERVAL),
+            last_check: None,
+        }
+    }
+
+    // Whether the interval is over since the last time it was, starting it over if so
+    pub fn ready(&mut self) -> bool {
+        let now = Instant::now();
+        if let Some(last_check) = self.last_check {
+            if now.duration_since(last_check) < self.interval {
+                return false;
+            }
+        }
+        self.last_check = Some(now);
+        true
+    }
+}
@@ -0,0 +1,43 @@
+use config::Config;
+use ggez::GameResult;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use throttle::Throttle;
+
+// Watches a configuration file, to tune a running game
+pub struct TuningWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    throttle: Throttle,
+}
+
+impl TuningWatcher {
+    pub fn new(path: PathBuf) -> TuningWatcher {
+        TuningWatcher {
+            path,
+            modified: None,
+            throttle: Throttle::new(),
+        }
+    }
+
+    // Returns the new configuration when the file changed since the last call,
+    // the entries missing from the file keep their value in config
+    pub fn poll(&mut self, config: &Config) -> GameResult<Option<Config>> {
+        if !self.throttle.ready() {
+            return Ok(None);
+        }
+
+        let modified = fs::metadata(&self.path)?.modified()?;
+        if self.modified == Some(modified) {
+            return Ok(None);
+        }
+        // even if the file is invalid, it is not read again until its next change
+        self.modified = Some(modified);
+
+        let mut content = String::new();
+        File::open(&self.path)?.read_to_string(&mut content)?;
+        config.parse_over(&content).map(Some)
+    }
+}
@@ -0,0 +1,246 @@
+use baddies::{Baddie, BaddieColor, BaddieFace, Movement, COLORS, FACES};
+use config::Level;
+use constants::*;
+use ggez::{Context, GameError, GameResult};
+use rand::Rng;
+use rand::distributions::{Range, Sample};
+use std::io::Read;
+use toml;
+
+pub const WAVES_PATH: &str = "/waves.toml";
+
+// Built-in wave set: random baddies, following the difficulty levels
+pub const ENDLESS: &str = "endless";
+
+// The arena is split in LANES lanes of the same width
+pub const LANES: usize = 8;
+
+// A group of baddies, spawned one after the other
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Wave {
+    pub count: u32,
+    // ticks between two baddies
+    pub interval: u32,
+    // ticks without any baddie after the wave
+    #[serde(default)]
+    pub pause: u32,
+    // picked at random, any of them when empty
+    #[serde(default)]
+    pub colors: Vec<BaddieColor>,
+    #[serde(default)]
+    pub faces: Vec<BaddieFace>,
+    // from 0 (left) to LANES - 1 (right)
+    #[serde(default)]
+    pub lanes: Vec<usize>,
+    // min and max
+    pub size: (f32, f32),
+    pub speed: (f32, f32),
+    // picked at random, straight when empty
+    #[serde(default)]
+    pub movements: Vec<Movement>,
+}
+
+// Layout of WAVES_PATH
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WavesFile {
+    #[serde(default)]
+    sets: Vec<WaveSet>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WaveSet {
+    name: String,
+    waves: Vec<Wave>,
+}
+
+// Loads the waves of a set from WAVES_PATH,
+// the endless set has no wave at all
+pub fn load(ctx: &mut Context, name: &str) -> GameResult<Vec<Wave>> {
+    if name == ENDLESS {
+        return Ok(Vec::new());
+    }
+
+    let mut content = String::new();
+    ctx.filesystem.open(WAVES_PATH)?.read_to_string(&mut content)?;
+    parse(&content, name)
+}
+
+pub fn parse(content: &str, name: &str) -> GameResult<Vec<Wave>> {
+    let file: WavesFile = toml::from_str(content).map_err(|e| invalid(e.to_string()))?;
+
+    let set = file.sets
+        .into_iter()
+        .find(|set| set.name == name)
+        .ok_or_else(|| invalid(format!("no wave set named '{}'", name)))?;
+
+    if set.waves.is_empty() {
+        return Err(invalid(format!("wave set '{}' has no wave", name)));
+    }
+    validate(&set.waves, name)?;
+
+    Ok(set.waves)
+}
+
+// Checks every wave of a set, name is only used in the errors
+pub(crate) fn validate(waves: &[Wave], name: &str) -> GameResult<()> {
+    for (i, wave) in waves.iter().enumerate() {
+        wave.validate(&format!("{}.waves[{}]", name, i))?;
+    }
+    Ok(())
+}
+
+fn invalid(message: String) -> GameError {
+    GameError::ConfigError(format!("Invalid waves: {}", message))
+}
+
+// Picks a value between min and max, both included when they are equal
+fn sample<R: Rng>(rng: &mut R, (min, max): (f32, f32)) -> f32 {
+    if min < max {
+        Range::new(min, max).sample(rng)
+    } else {
+        min
+    }
+}
+
+fn pick<T: Copy, R: Rng>(rng: &mut R, choices: &[T], all: &[T]) -> T {
+    let choices = if choices.is_empty() { all } else { choices };
+    choices[rng.gen_range(0, choices.len())]
+}
+
+impl Wave {
+    fn validate(&self, name: &str) -> GameResult<()> {
+        if self.count == 0 || self.interval == 0 {
+            return Err(invalid(format!(
+                "{}: count and interval must be positive",
+                name
+            )));
+        }
+
+        let lane_width = WIDTH / LANES as f32;
+        let (min_size, max_size) = self.size;
+        if min_size.is_nan() || min_size <= 0.0 || max_size < min_size || max_size > lane_width {
+            return Err(invalid(format!(
+                "{}: size must be between 0 and {}, the width of a lane",
+                name, lane_width
+            )));
+        }
+
+        let (min_speed, max_speed) = self.speed;
+        if min_speed.is_nan() || min_speed <= 0.0 || max_speed < min_speed {
+            return Err(invalid(format!("{}: speed must be positive", name)));
+        }
+
+        if let Some(lane) = self.lanes.iter().find(|&&lane| lane >= LANES) {
+            return Err(invalid(format!(
+                "{}: lane {} doesn't exist, the last one is {}",
+                name,
+                lane,
+                LANES - 1
+            )));
+        }
+
+        for movement in &self.movements {
+            movement
+                .validate()
+                .map_err(|e| invalid(format!("{}: {}", name, e)))?;
+        }
+
+        Ok(())
+    }
+
+    fn spawn<R: Rng>(&self, rng: &mut R) -> Baddie {
+        let lanes: Vec<_> = (0..LANES).collect();
+        let lane = pick(rng, &self.lanes, &lanes);
+        let size = sample(rng, self.size);
+        let speed = sample(rng, self.speed);
+
+        // anywhere in the lane
+        let lane_width = WIDTH / LANES as f32;
+        let x = lane as f32 * lane_width + sample(rng, (0.0, lane_width - size));
+
+        let color = pick(rng, &self.colors, &COLORS);
+        let face = pick(rng, &self.faces, &FACES);
+        let movement = Movement::pick(rng, &self.movements);
+        Baddie::spawn(x, size, speed, color, face, movement)
+    }
+}
+
+// Spawns the baddies of the waves, one after the other, and starts over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaveScheduler {
+    waves: Vec<Wave>,
+    // current wave and the baddies it already spawned
+    wave: usize,
+    spawned: u32,
+    // ticks before the next baddie
+    countdown: u32,
+}
+
+impl WaveScheduler {
+    // Without any wave, baddies are spawned endlessly
+    pub fn new(waves: Vec<Wave>) -> WaveScheduler {
+        WaveScheduler {
+            waves,
+            wave: 0,
+            spawned: 0,
+            countdown: 0,
+        }
+    }
+
+    // Starts over from the first wave
+    pub fn restart(&mut self) {
+        self.wave = 0;
+        self.spawned = 0;
+        self.countdown = 0;
+    }
+
+    pub fn waves(&self) -> &[Wave] {
+        &self.waves
+    }
+
+    pub fn is_endless(&self) -> bool {
+        self.waves.is_empty()
+    }
+
+    // Index of the current wave, if the waves are scripted
+    pub fn wave(&self) -> Option<usize> {
+        if self.is_endless() {
+            None
+        } else {
+            Some(self.wave)
+        }
+    }
+
+    // Called once per tick, returns the baddie to spawn, if any.
+    // level and timer only matter to the endless waves.
+    pub fn update<R: Rng>(&mut self, rng: &mut R, level: &Level, timer: u32) -> Option<Baddie> {
+        if self.is_endless() {
+            return if timer % level.spawn_frequency == 0 {
+                Some(Baddie::new(rng, level))
+            } else {
+                None
+            };
+        }
+
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return None;
+        }
+
+        let wave = &self.waves[self.wave];
+        let baddie = wave.spawn(rng);
+
+        self.spawned += 1;
+        self.countdown = wave.interval - 1;
+        if self.spawned == wave.count {
+            self.countdown += wave.pause;
+            self.spawned = 0;
+            self.wave = (self.wave + 1) % self.waves.len();
+        }
+
+        Some(baddie)
+    }
+}